@@ -1,6 +1,7 @@
 use clap::{App, Arg};
 use fs::{BlockDevice, FileSystem};
-use std::fs::{read_dir, File, OpenOptions};
+use std::fs::{metadata, read_dir, File, OpenOptions};
+use std::os::unix::fs::FileTypeExt;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -73,8 +74,15 @@ fn fs_pack() -> std::io::Result<()> {
     root_inode.mkdir("bin");
     let bin_inode = root_inode.find("bin").unwrap();
     for app in apps {
+        // host FIFOs become FIFOs in fs, opening them here would block
+        let host_path = format!("{}{}", target_path, app);
+        if metadata(&host_path)?.file_type().is_fifo() {
+            bin_inode.mkfifo(app.as_str()).unwrap();
+            println!("Created fifo: {}", app);
+            continue;
+        }
         // load app data from host file system
-        let mut host_file = File::open(format!("{}{}", target_path, app)).unwrap();
+        let mut host_file = File::open(host_path).unwrap();
         let mut all_data: Vec<u8> = Vec::new();
         host_file.read_to_end(&mut all_data).unwrap();
         // create a file in fs
@@ -238,6 +246,24 @@ fn fs_test() -> std::io::Result<()> {
         println!("/usr/modist: {}", name);
     }
 
+    println!("Testing creating fifo in /usr...");
+    let fifo_inode = usr_inode.mkfifo("fifo").unwrap();
+    assert!(fifo_inode.is_fifo(), "fifo should be a FIFO!");
+    assert!(usr_inode.mkfifo("fifo").is_none(), "fifo should exist already!");
+    assert!(usr_inode.ls().contains(&String::from("fifo|")), "ls should mark fifo!");
+    assert!(!usr_inode.find("modist").unwrap().is_fifo(), "modist is not a FIFO!");
+    usr_inode.remove("fifo");
+    assert!(usr_inode.find("fifo").is_none(), "fifo should be removed!");
+
+    println!("Testing creating socket in /usr...");
+    let sock_inode = usr_inode.mksock("sock").unwrap();
+    assert!(sock_inode.is_socket(), "sock should be a socket!");
+    assert!(!sock_inode.is_fifo(), "sock is not a FIFO!");
+    assert!(usr_inode.mksock("sock").is_none(), "sock should exist already!");
+    assert!(usr_inode.ls().contains(&String::from("sock=")), "ls should mark sock!");
+    usr_inode.remove("sock");
+    assert!(usr_inode.find("sock").is_none(), "sock should be removed!");
+
     println!("Testing writing and reading filea...");
     let filea = par_node.find("filea").unwrap();
     let greet_str = "Hello, world!";
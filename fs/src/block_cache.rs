@@ -2,6 +2,7 @@ use alloc::sync::Arc;
 use lazy_static::*;
 
 use alloc::collections::VecDeque;
+use alloc::vec::Vec;
 use spin::Mutex;
 
 use crate::{BLOCK_SZ, block_dev::BlockDevice};
@@ -21,10 +22,29 @@ pub fn get_block_cache(
         .get_block_cache(block_id, block_device)
 }
 
+/// Write back every dirty block. All writes are queued before waiting for
+/// any, so a device with a request queue has them in flight together.
 pub fn block_cache_sync_all() {
     let manager = BLOCK_CACHE_MANAGER.lock();
-    for (_, cache) in manager.queue.iter() {
-        cache.lock().sync();
+    // the blocks stay locked until written, the device reads them meanwhile
+    let mut dirty: Vec<_> = manager
+        .queue
+        .iter()
+        .map(|(_, cache)| cache.lock())
+        .filter(|cache| cache.modified)
+        .collect();
+    let requests: Vec<usize> = dirty
+        .iter_mut()
+        .map(|cache| {
+            cache.modified = false;
+            unsafe { cache.block_device.submit_write(cache.block_id, &cache.cache) }
+        })
+        .collect();
+    for (cache, request) in dirty.iter_mut().zip(requests) {
+        // a failed write is tried again at the next sync
+        if !cache.block_device.wait_for(request) {
+            cache.modified = true;
+        }
     }
 }
 
@@ -95,8 +115,12 @@ impl Drop for BlockCache {
     }
 }
 
+/// A cached block, keyed by the address of its device and the block id
+type CacheEntry = ((usize, usize), Arc<Mutex<BlockCache>>);
+
+/// Blocks of every device
 pub struct BlockCacheManager {
-    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+    queue: VecDeque<CacheEntry>,
 }
 
 impl BlockCacheManager {
@@ -111,8 +135,9 @@ impl BlockCacheManager {
         block_id: usize,
         block_device: Arc<dyn BlockDevice>,
     ) -> Arc<Mutex<BlockCache>> {
+        let key = (Arc::as_ptr(&block_device) as *const () as usize, block_id);
         for (id, cache) in self.queue.iter() {
-            if *id == block_id {
+            if *id == key {
                 return Arc::clone(cache);
             }
         }
@@ -131,7 +156,7 @@ impl BlockCacheManager {
             block_id,
             Arc::clone(&block_device),
         )));
-        self.queue.push_back((block_id, Arc::clone(&cache)));
+        self.queue.push_back((key, Arc::clone(&cache)));
         cache
     }
 }
@@ -5,4 +5,29 @@ pub trait BlockDevice : Send + Sync + Any {
     fn read_block(&self, block_id: usize, buf: &mut [u8]);
     /// Write from buffer to block
     fn write_block(&self, block_id: usize, buf: &[u8]);
-}
\ No newline at end of file
+    /// Queue a read of `block_id` into `buf` and return the request to `wait_for`.
+    /// A device without a request queue reads right away.
+    ///
+    /// # Safety
+    /// `buf` must stay alive and untouched until the request completes.
+    unsafe fn submit_read(&self, block_id: usize, buf: &mut [u8]) -> usize {
+        self.read_block(block_id, buf);
+        0
+    }
+    /// Queue a write of `buf` to `block_id`, see `submit_read`.
+    ///
+    /// # Safety
+    /// `buf` must stay alive until the request completes.
+    unsafe fn submit_write(&self, block_id: usize, buf: &[u8]) -> usize {
+        self.write_block(block_id, buf);
+        0
+    }
+    /// Wait until a submitted request completes, false if the device failed it
+    fn wait_for(&self, _request: usize) -> bool {
+        true
+    }
+    /// Size of the device in blocks, None if it cannot tell
+    fn num_blocks(&self) -> Option<usize> {
+        None
+    }
+}
@@ -79,11 +79,18 @@ impl FileSystem {
    
 
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
+        Self::try_open(block_device).expect("Error loading EFS!")
+    }
+
+    /// Open the file system on `block_device`, None if there is none
+    pub fn try_open(block_device: Arc<dyn BlockDevice>) -> Option<Arc<Mutex<Self>>> {
         // read SuperBlock
         get_block_cache(0, Arc::clone(&block_device))
             .lock()
             .read(0, |super_block: &SuperBlock| {
-                assert!(super_block.is_valid(), "Error loading EFS!");
+                if !super_block.is_valid() {
+                    return None;
+                }
                 let inode_total_blocks =
                     super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
                 let fs = Self {
@@ -99,7 +106,7 @@ impl FileSystem {
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
                 };
-                Arc::new(Mutex::new(fs))
+                Some(Arc::new(Mutex::new(fs)))
             })
     }
 
@@ -64,6 +64,10 @@ pub struct DiskInode {
 pub enum DiskInodeType {
     File,
     Directory,
+    /// named pipe, it never holds data on disk
+    Fifo,
+    /// address of a bound unix-domain socket, holds no data either
+    Socket,
 }
 
 impl DiskInode {
@@ -84,6 +88,14 @@ impl DiskInode {
         self.type_ == DiskInodeType::File
     }
 
+    pub fn is_fifo(&self) -> bool {
+        self.type_ == DiskInodeType::Fifo
+    }
+
+    pub fn is_socket(&self) -> bool {
+        self.type_ == DiskInodeType::Socket
+    }
+
     // block_id is a pointer to the physical block
     // inner_id is the index of the block in the file led by dinode
     pub fn get_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
@@ -142,11 +142,11 @@ impl Inode {
         Some(cur_inode)
     }
 
-    pub fn ls(&self) -> Vec<String> {
-        let _fs = self.fs.lock();
+    /// entries of the directory that are in use
+    fn dirents(&self) -> Vec<DirEntry> {
         self.read_disk_inode(|disk_inode| {
             let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-            let mut v: Vec<String> = Vec::new();
+            let mut v: Vec<DirEntry> = Vec::new();
             for i in 0..file_count {
                 let mut dirent = DirEntry::empty();
                 assert_eq!(
@@ -157,14 +157,86 @@ impl Inode {
                 if dirent.is_empty() {
                     continue;
                 }
-                v.push(String::from(dirent.name()));
+                v.push(dirent);
             }
             v
         })
     }
 
+    /// names in the directory as they are, `.` and `..` too
+    pub fn names(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.dirents()
+            .iter()
+            .map(|dirent| String::from(dirent.name()))
+            .collect()
+    }
+
+    /// list names in the directory, FIFOs are marked with a trailing `|`
+    /// and sockets with a trailing `=`
+    pub fn ls(&self) -> Vec<String> {
+        let fs = self.fs.lock();
+        let dirents = self.dirents();
+        // an entry may share the block of this inode, so look at types after the read
+        dirents
+            .iter()
+            .map(|dirent| {
+                let (block_id, block_offset) = fs.get_disk_inode_pos(dirent.inode_number());
+                let (is_fifo, is_socket) =
+                    get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                        .lock()
+                        .read(block_offset, |disk_inode: &DiskInode| {
+                            (disk_inode.is_fifo(), disk_inode.is_socket())
+                        });
+                let mut name = String::from(dirent.name());
+                if is_fifo {
+                    name.push('|');
+                } else if is_socket {
+                    name.push('=');
+                }
+                name
+            })
+            .collect()
+    }
+
+    /// position of the disk inode, unique for each file in the file system
+    pub fn disk_position(&self) -> (usize, usize) {
+        (self.block_id, self.block_offset)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+
+    /// size in bytes, of the entries for a directory
+    pub fn size(&self) -> usize {
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+
+    pub fn is_fifo(&self) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.is_fifo())
+    }
+
+    /// create a new FIFO, but every dir should exist before the last one
+    pub fn mkfifo(&self, path: &str) -> Option<Arc<Inode>> {
+        self.create_typed(path, DiskInodeType::Fifo)
+    }
+
+    pub fn is_socket(&self) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.is_socket())
+    }
+
+    /// create a new socket address, but every dir should exist before the last one
+    pub fn mksock(&self, path: &str) -> Option<Arc<Inode>> {
+        self.create_typed(path, DiskInodeType::Socket)
+    }
+
     /// create a new file, but every dir should exist before the last one
     pub fn create(&self, path: &str) -> Option<Arc<Inode>> {
+        self.create_typed(path, DiskInodeType::File)
+    }
+
+    fn create_typed(&self, path: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
         // create a new file, but every dir should exist before the last one
         if path.is_empty() {
             return None;
@@ -181,7 +253,7 @@ impl Inode {
                 return None; // invalid absolute path
             }
             if i == len - 1 {
-                return cur_inode._create(name); // create the new file
+                return cur_inode._create(name, type_); // create the new file
             } else {
                 if let Some(next_inode) = cur_inode._find(name) {
                     cur_inode = next_inode;
@@ -193,7 +265,7 @@ impl Inode {
         None
     }
 
-    fn _create(&self, name: &str) -> Option<Arc<Inode>> {
+    fn _create(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
         let mut fs = self.fs.lock();
         let op = |cur_dir_inode: &DiskInode| {
             // assert it is a directory
@@ -212,7 +284,7 @@ impl Inode {
         get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(DiskInodeType::File);
+                new_inode.initialize(type_);
             });
         self.modify_disk_inode(|cur_dir_inode| {
             // append file in the dirent
@@ -593,6 +665,22 @@ impl Inode {
                     &self.block_device,
                 );
             });
+            // .. of a moved directory -> its new parent
+            let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+            let moved_inode = Self::new(
+                block_id,
+                block_offset,
+                Arc::clone(&self.fs),
+                Arc::clone(&self.block_device),
+            );
+            let parent_inode_id =
+                fs.get_inode_id(dst_inode.block_id as u32, dst_inode.block_offset);
+            moved_inode.modify_disk_inode(|disk_inode| {
+                if disk_inode.is_dir() {
+                    let dirent = DirEntry::new("..", parent_inode_id);
+                    disk_inode.write_at(DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+                }
+            });
             block_cache_sync_all();
             true
         } else {
@@ -1,16 +1,23 @@
 use std::fs::File;
 use std::io::{Result, Write};
 
+#[path = "src/sha256.rs"]
+mod sha256;
+
 fn main() {
     println!("cargo:rerun-if-changed=../sup/src/");
     println!("cargo:rerun-if-changed={}", TARGET_PATH);
     insert_kernel_data().unwrap();
 }
 
-static TARGET_PATH: &str = "../sup/target/riscv64gc-unknown-none-elf/release/sup.bin";
+/// the stripped kernel ELF, made by `make build` in sup
+static TARGET_PATH: &str = "../sup/target/riscv64gc-unknown-none-elf/release/sup.elf";
 
 fn insert_kernel_data() -> Result<()> {
     let mut f = File::create("../os/src/link_kernel.S").unwrap();
+    // the loader checks the image against this before it runs it
+    let digest = sha256::sha256(&std::fs::read(TARGET_PATH)?);
+    let digest: Vec<String> = digest.iter().map(|byte| format!("{:#04x}", byte)).collect();
 
     writeln!(
         f,
@@ -21,8 +28,14 @@ fn insert_kernel_data() -> Result<()> {
     .global kernel_end
 kernel_start:
     .incbin "{}"
-kernel_end:"#,
-        TARGET_PATH
+kernel_end:
+
+    .section .rodata
+    .global kernel_sha256
+kernel_sha256:
+    .byte {}"#,
+        TARGET_PATH,
+        digest.join(", ")
     )?;
     Ok(())
 }
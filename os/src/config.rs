@@ -1,9 +1,15 @@
 pub const MTIME: *const u64 = 0x0200bff8 as *const u64;
 pub const MTIMECMP: *mut u64 = 0x02004000 as *mut u64;
-pub const TIME_INTERVAL: u64 = 200000;
 
+/// this image, its stacks and the copy of the kernel, closed to S and U-mode
+pub const MACHINE_START: usize = 0x80000000;
+pub const MACHINE_SIZE: usize = 0x200000;
+/// the kernel may only be loaded above
+pub const SUPERVISOR_START: usize = MACHINE_START + MACHINE_SIZE;
 pub const MACHINE_STACK_SIZE: usize = 4096 * 2;
-pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
+/// end of the 128M of RAM the Makefile starts QEMU with, the kernel has to
+/// fit below it
+pub const MEMORY_END: usize = 0x88000000;
 
 pub const CLOCK_FREQ: usize = 12500000;
 pub const TICKS_PER_SEC: usize = 100;
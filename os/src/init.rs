@@ -1,6 +1,6 @@
 use core::arch::asm;
 use riscv::register::*;
-use crate::trap::*;
+use crate::config::*;
 
 pub fn switch_s(s_mode_entry: usize, hartid: usize) {
     unsafe {
@@ -10,17 +10,19 @@ pub fn switch_s(s_mode_entry: usize, hartid: usize) {
 
         satp::write(0);
 
-        asm!("csrw medeleg, {}", in(reg) !0);
+        // ecalls from S-mode stay here, they are SBI calls, and so do illegal
+        // instructions and misaligned loads and stores, to be emulated
+        asm!("csrw medeleg, {}", in(reg) !(1usize << 2 | 1 << 4 | 1 << 6 | 1 << 9));
         asm!("csrw mideleg, {}", in(reg) !0);
 
         sie::set_sext();
         sie::set_ssoft();
         sie::set_stimer();
 
-        pmpaddr0::write(0x3fffffffffffffusize);
-        pmpcfg0::write(0xf);
+        set_pmp();
 
-        set_next_trigger(0);
+        // no timer until the supervisor asks for one
+        (MTIMECMP as *mut u64).write_volatile(u64::MAX);
 
         unsafe extern "C" {
             safe fn __alltraps_m();
@@ -31,6 +33,25 @@ pub fn switch_s(s_mode_entry: usize, hartid: usize) {
         );
 
         mstatus::set_mie();
-        mie::set_mtimer();
     };
 }
+
+const PMP_NAPOT: usize = 3 << 3;
+const PMP_RWX: usize = 0x7;
+
+/// Entry 0 denies S and U-mode the machine area, entry 1 allows them all
+/// the rest. Lower entries win, M-mode is not held back by unlocked ones.
+fn set_pmp() {
+    unsafe extern "C" {
+        safe fn emachine();
+    }
+    assert!(
+        emachine as usize <= MACHINE_START + MACHINE_SIZE,
+        "machine image ends at {:#x}, past the area PMP protects",
+        emachine as usize
+    );
+    // NAPOT: the trailing ones of the address give the size
+    pmpaddr0::write(MACHINE_START >> 2 | (MACHINE_SIZE >> 3) - 1);
+    pmpaddr1::write(0x3fffffffffffffusize);
+    pmpcfg0::write(PMP_NAPOT | (PMP_NAPOT | PMP_RWX) << 8);
+}
@@ -1,32 +1,23 @@
 use core::arch::asm;
+use core::fmt;
 
 use crate::config::*;
 use crate::init::switch_s;
+use crate::sbi::shutdown;
+use crate::sha256::sha256;
 use crate::trap::TrapContext;
 use log::info;
 
-const KERNEL_BASE_ADDRESS: usize = 0x80200000;
-
 #[repr(align(4096))]
 #[derive(Copy, Clone)]
 struct MachineStack {
     data: [u8; MACHINE_STACK_SIZE],
 }
 
-#[repr(align(4096))]
-#[derive(Copy, Clone)]
-struct KernelStack {
-    data: [u8; KERNEL_STACK_SIZE],
-}
-
 static MACHINE_STACK: MachineStack = MachineStack {
     data: [0; MACHINE_STACK_SIZE],
 };
 
-static KERNEL_STACK: KernelStack = KernelStack {
-    data: [0; KERNEL_STACK_SIZE],
-};
-
 impl MachineStack {
     fn get_sp(&self) -> usize {
         self.data.as_ptr() as usize + MACHINE_STACK_SIZE
@@ -41,44 +32,117 @@ impl MachineStack {
     }
 }
 
-impl KernelStack {
-    fn get_sp(&self) -> usize {
-        self.data.as_ptr() as usize + KERNEL_STACK_SIZE
-    }
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const EM_RISCV: u16 = 0xf3;
+
+fn read_u16(image: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(image.get(offset..offset + 2)?.try_into().ok()?))
 }
 
-pub fn load_kernel() {
-    unsafe extern "C" {
-        safe fn kernel_start();
-        safe fn kernel_end();
-    }
+fn read_u32(image: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(image.get(offset..offset + 4)?.try_into().ok()?))
+}
 
-    let kernel_start_ptr = kernel_start as usize as *const usize;
-    let kernel_end_ptr = kernel_end as usize as *const usize;
-    let kernel_size = kernel_end_ptr as usize - kernel_start_ptr as usize;
-    // info!("[kernel] kernel asm size: {:#x}", kernel_size);
+fn read_u64(image: &[u8], offset: usize) -> Option<usize> {
+    Some(u64::from_le_bytes(image.get(offset..offset + 8)?.try_into().ok()?) as usize)
+}
 
-    (KERNEL_BASE_ADDRESS..KERNEL_BASE_ADDRESS + kernel_size)
-        .for_each(|addr| unsafe { (addr as *mut u8).write_volatile(0) });
+struct Hex<'a>(&'a [u8]);
 
-    let src = unsafe { core::slice::from_raw_parts(kernel_start_ptr as *const u8, kernel_size) };
-    let dst =
-        unsafe { core::slice::from_raw_parts_mut(KERNEL_BASE_ADDRESS as *mut u8, kernel_size) };
-    dst.copy_from_slice(src);
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
 
+/// Place the `PT_LOAD` segments of the ELF `image` at their physical addresses
+/// and zero what the file leaves out, like `.bss`. Gives the entry, None if
+/// the image is not a RISC-V ELF64 that fits between the machine area and the
+/// end of memory, or its entry is not in an executable segment.
+fn load_elf(image: &[u8]) -> Option<usize> {
+    if image.get(..6)? != b"\x7fELF\x02\x01" || read_u16(image, 18)? != EM_RISCV {
+        return None;
+    }
+    let entry = read_u64(image, 24)?;
+    let phoff = read_u64(image, 32)?;
+    let phentsize = read_u16(image, 54)? as usize;
+    let phnum = read_u16(image, 56)? as usize;
+    let mut entry_loaded = false;
+    for i in 0..phnum {
+        let ph = phoff.checked_add(i.checked_mul(phentsize)?)?;
+        if read_u32(image, ph)? != PT_LOAD {
+            continue;
+        }
+        let flags = read_u32(image, ph + 4)?;
+        let offset = read_u64(image, ph + 8)?;
+        let paddr = read_u64(image, ph + 24)?;
+        let filesz = read_u64(image, ph + 32)?;
+        let memsz = read_u64(image, ph + 40)?;
+        let data = image.get(offset..offset.checked_add(filesz)?)?;
+        let end = paddr.checked_add(memsz)?;
+        if paddr < SUPERVISOR_START || end > MEMORY_END || filesz > memsz {
+            return None;
+        }
+        if flags & PF_X != 0 && (paddr..end).contains(&entry) {
+            entry_loaded = true;
+        }
+        let dst = unsafe { core::slice::from_raw_parts_mut(paddr as *mut u8, memsz) };
+        dst[..filesz].copy_from_slice(data);
+        dst[filesz..].fill(0);
+    }
+    if !entry_loaded {
+        return None;
+    }
     unsafe {
         asm!("fence.i");
     }
+    Some(entry)
+}
+
+/// Check the embedded kernel against the digest `build.rs` recorded and load
+/// it, gives its entry. A stale or broken image powers off.
+pub fn load_kernel() -> usize {
+    unsafe extern "C" {
+        safe fn kernel_start();
+        safe fn kernel_end();
+        #[link_name = "kernel_sha256"]
+        safe static KERNEL_SHA256: [u8; 32];
+    }
+
+    let kernel_size = kernel_end as usize - kernel_start as usize;
+    let image = unsafe { core::slice::from_raw_parts(kernel_start as usize as *const u8, kernel_size) };
+    let digest = sha256(image);
+    if digest != KERNEL_SHA256 {
+        println!(
+            "[loader] kernel image does not match its SHA-256\n  expected {}\n  found    {}",
+            Hex(&KERNEL_SHA256),
+            Hex(&digest)
+        );
+        shutdown(true);
+    }
+    match load_elf(image) {
+        Some(entry) => {
+            info!("[loader] kernel verified and loaded, entry {:#x}", entry);
+            entry
+        }
+        None => {
+            println!("[loader] kernel image is not an ELF that can be loaded");
+            shutdown(true)
+        }
+    }
 }
 
-pub fn run_kernel() {
-    switch_s(0x80200000, 0);
-    
+/// Enter the kernel with the hart id and device tree in a0 and a1, like SBI firmware.
+/// It sets up its own stack, none in here would be open to it.
+pub fn run_kernel(hartid: usize, dtb: usize, entry: usize) {
+    switch_s(entry, hartid);
+
+    let mut ctx = TrapContext::kernel_init_context(entry, 0);
+    ctx.x[10] = hartid;
+    ctx.x[11] = dtb;
     unsafe extern "C" {
         safe fn __restore_m(ctx_addr: usize);
     }
-    __restore_m(MACHINE_STACK.push_context(TrapContext::kernel_init_context(
-        0x80200000,
-        KERNEL_STACK.get_sp(),
-    )) as *const _ as usize);
+    __restore_m(MACHINE_STACK.push_context(ctx) as *const _ as usize);
 }
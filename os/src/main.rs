@@ -10,6 +10,7 @@ mod init;
 mod lang_items;
 mod loader;
 mod logging;
+mod sha256;
 mod trap;
 mod uart;
 
@@ -25,14 +26,15 @@ pub fn clear_bss() {
     (sbss as usize..ebss as usize).for_each(|a| unsafe { (a as *mut u8).write_volatile(0) });
 }
 
-/// the rust entry-point of m mode, at 0x80000000
+/// the rust entry-point of m mode, at 0x80000000.
+/// QEMU leaves the hart id in a0 and the device tree in a1.
 #[unsafe(no_mangle)]
-pub fn rust_main() -> ! {
+pub fn rust_main(hartid: usize, dtb: usize) -> ! {
     clear_bss();
     sbi::init_uart();
     logging::init();
     log::info!("[kernel] POWERON");
-    loader::load_kernel();
-    loader::run_kernel();
+    let entry = loader::load_kernel();
+    loader::run_kernel(hartid, dtb, entry);
     sbi::shutdown(false)
 }
@@ -45,6 +45,26 @@ pub fn scan() -> u8 {
     }
 }
 
+/// SBI console output of the supervisor
+pub fn console_putchar(c: u8) {
+    unsafe {
+        if let Some(ref mut uart) = MSP {
+            uart.send(c);
+        }
+    }
+}
+
+/// SBI console input of the supervisor, None if nothing was received
+pub fn console_getchar() -> Option<u8> {
+    unsafe {
+        if let Some(ref mut uart) = MSP {
+            uart.try_receive()
+        } else {
+            None
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! print {
     ($fmt: literal $(, $($arg: tt)+)?) => {
@@ -0,0 +1,180 @@
+//! Emulation of what the hardware may leave to M-mode: misaligned loads and
+//! stores, and reads of the `time` CSR. Anything else goes on to S-mode.
+use super::TrapContext;
+use crate::config::*;
+use core::arch::asm;
+use riscv::register::mstatus;
+
+// exception codes of mcause
+const LOAD_ACCESS_FAULT: usize = 5;
+const LOAD_PAGE_FAULT: usize = 13;
+const INSTRUCTION_ACCESS_FAULT: usize = 1;
+const INSTRUCTION_PAGE_FAULT: usize = 12;
+
+const CSR_TIME: u32 = 0xc01;
+
+/// An exception to hand to S-mode, mcause and mtval
+pub type Fault = (usize, usize);
+
+#[repr(C)]
+struct MprvResult {
+    value: usize,
+    /// 0, or the mcause of the fault
+    cause: usize,
+}
+
+unsafe extern "C" {
+    safe fn __mprv_lbu(addr: usize) -> MprvResult;
+    safe fn __mprv_sb(addr: usize, byte: usize) -> MprvResult;
+}
+
+/// Load `len` bytes at `addr`, little endian
+fn load(addr: usize, len: usize) -> Result<usize, Fault> {
+    let mut value = 0;
+    for i in (0..len).rev() {
+        let result = __mprv_lbu(addr + i);
+        if result.cause != 0 {
+            return Err((result.cause, addr + i));
+        }
+        value = value << 8 | result.value;
+    }
+    Ok(value)
+}
+
+fn store(addr: usize, len: usize, value: usize) -> Result<(), Fault> {
+    for i in 0..len {
+        let result = __mprv_sb(addr + i, value >> (8 * i) & 0xff);
+        if result.cause != 0 {
+            return Err((result.cause, addr + i));
+        }
+    }
+    Ok(())
+}
+
+/// The instruction at `pc`, which may be compressed and only 2-aligned
+fn fetch(pc: usize) -> Result<u32, Fault> {
+    let as_fetch_fault = |(cause, _)| match cause {
+        LOAD_PAGE_FAULT => (INSTRUCTION_PAGE_FAULT, pc),
+        LOAD_ACCESS_FAULT => (INSTRUCTION_ACCESS_FAULT, pc),
+        cause => (cause, pc),
+    };
+    let low = load(pc, 2).map_err(as_fetch_fault)? as u32;
+    if low & 3 != 3 {
+        return Ok(low);
+    }
+    let high = load(pc + 2, 2).map_err(as_fetch_fault)? as u32;
+    Ok(high << 16 | low)
+}
+
+fn insn_len(insn: u32) -> usize {
+    if insn & 3 == 3 { 4 } else { 2 }
+}
+
+enum Access {
+    Load { rd: usize, len: usize, signed: bool },
+    Store { rs: usize, len: usize },
+}
+
+/// The integer load or store `insn` is, also the compressed ones of RV64C
+fn decode_access(insn: u32) -> Option<Access> {
+    let reg = |shift: u32| (insn >> shift & 0x1f) as usize;
+    // rd' and rs2' of the compressed forms
+    let creg = || (insn >> 2 & 7) as usize + 8;
+    if insn & 3 == 3 {
+        let funct3 = insn >> 12 & 7;
+        return match insn & 0x7f {
+            0x03 if funct3 != 7 => Some(Access::Load {
+                rd: reg(7),
+                len: 1 << (funct3 & 3),
+                signed: funct3 < 4,
+            }),
+            0x23 if funct3 < 4 => Some(Access::Store {
+                rs: reg(20),
+                len: 1 << funct3,
+            }),
+            _ => None,
+        };
+    }
+    match (insn & 3, insn >> 13 & 7) {
+        // c.lw, c.ld, c.sw, c.sd
+        (0, 2) => Some(Access::Load { rd: creg(), len: 4, signed: true }),
+        (0, 3) => Some(Access::Load { rd: creg(), len: 8, signed: true }),
+        (0, 6) => Some(Access::Store { rs: creg(), len: 4 }),
+        (0, 7) => Some(Access::Store { rs: creg(), len: 8 }),
+        // c.lwsp, c.ldsp, c.swsp, c.sdsp
+        (2, 2) => Some(Access::Load { rd: reg(7), len: 4, signed: true }),
+        (2, 3) => Some(Access::Load { rd: reg(7), len: 8, signed: true }),
+        (2, 6) => Some(Access::Store { rs: reg(2), len: 4 }),
+        (2, 7) => Some(Access::Store { rs: reg(2), len: 8 }),
+        _ => None,
+    }
+}
+
+fn set_reg(ctx: &mut TrapContext, reg: usize, value: usize) {
+    if reg != 0 {
+        ctx.x[reg] = value;
+    }
+}
+
+/// Do the misaligned load or store at `addr` that raised `cause` byte by byte
+pub fn emulate_misaligned(ctx: &mut TrapContext, cause: usize, addr: usize) -> Result<(), Fault> {
+    let insn = fetch(ctx.mepc)?;
+    match decode_access(insn) {
+        Some(Access::Load { rd, len, signed }) => {
+            let mut value = load(addr, len)?;
+            if signed && len < 8 {
+                let shift = 64 - 8 * len;
+                value = ((value << shift) as isize >> shift) as usize;
+            }
+            set_reg(ctx, rd, value);
+        }
+        Some(Access::Store { rs, len }) => store(addr, len, ctx.x[rs])?,
+        // float and atomic accesses are not emulated
+        None => return Err((cause, addr)),
+    }
+    ctx.mepc += insn_len(insn);
+    Ok(())
+}
+
+/// Answer a read of the `time` CSR from `mtime`, `rdtime` and the other forms
+/// of csrrs/csrrc that change nothing. Other illegal instructions are a fault.
+pub fn emulate_illegal(ctx: &mut TrapContext, cause: usize, tval: usize) -> Result<(), Fault> {
+    let insn = fetch(ctx.mepc)?;
+    let funct3 = insn >> 12 & 7;
+    let rs1 = insn >> 15 & 0x1f;
+    let reads_time = insn & 0x7f == 0x73
+        && matches!(funct3, 2 | 3 | 6 | 7)
+        && rs1 == 0
+        && insn >> 20 == CSR_TIME;
+    if !reads_time {
+        return Err((cause, tval));
+    }
+    let time = unsafe { MTIME.read_volatile() } as usize;
+    set_reg(ctx, (insn >> 7 & 0x1f) as usize, time);
+    ctx.mepc += 4;
+    Ok(())
+}
+
+/// Pass the exception on to the S-mode trap handler, as if it had been delegated
+pub fn redirect_to_s(ctx: &mut TrapContext, (cause, tval): Fault) {
+    let previous = ctx.mstatus;
+    unsafe {
+        asm!(
+            "csrw scause, {cause}",
+            "csrw stval, {tval}",
+            "csrw sepc, {epc}",
+            cause = in(reg) cause,
+            tval = in(reg) tval,
+            epc = in(reg) ctx.mepc,
+        );
+        let stvec: usize;
+        asm!("csrr {}, stvec", out(reg) stvec);
+        ctx.mepc = stvec & !3;
+        // sstatus takes SPP and SPIE as a trap into S-mode would set them
+        let from_s = previous.mpp() == mstatus::MPP::Supervisor;
+        asm!("csrc sstatus, {}", in(reg) 1usize << 8 | 1 << 5 | 1 << 1);
+        asm!("csrs sstatus, {}", in(reg) (from_s as usize) << 8 | (previous.sie() as usize) << 5);
+        mstatus::set_mpp(mstatus::MPP::Supervisor);
+    }
+    ctx.mstatus = mstatus::read();
+}
@@ -1,4 +1,6 @@
 mod context;
+mod emulate;
+mod sbi;
 mod timer;
 
 use crate::{config::*, sbi::shutdown};
@@ -7,11 +9,10 @@ use core::arch::{asm, global_asm};
 use log::{error, info};
 use riscv::register::{
     mcause::{self, Exception, Interrupt, Trap},
-    mip, mtval,
+    mie, mip, mtval,
     mtvec::{self, TrapMode},
     scause, sip, stval, time,
 };
-pub use timer::*;
 
 global_asm!(include_str!("trap_m.S"));
 
@@ -28,7 +29,9 @@ pub fn init() {
 #[unsafe(no_mangle)]
 pub fn trap_handler_m(ctx: &mut TrapContext) {
     let mip = mip::read();
-    let mcause = mcause::read().cause();
+    let cause = mcause::read();
+    let mcause = cause.cause();
+    let code = cause.code();
     let mtval = mtval::read();
     // info!("end: {}", time::read());
     // error!(
@@ -39,9 +42,30 @@ pub fn trap_handler_m(ctx: &mut TrapContext) {
     match mcause {
         Trap::Interrupt(Interrupt::MachineTimer) => {
             // info!("Machine Timer Interrupt at {}", time::read());
-            set_next_trigger(0);
+            // pass it on as STIP, until the supervisor sets the next timer
             unsafe {
-                asm!("csrw sip, 2");
+                mie::clear_mtimer();
+                mip::set_stimer();
+            }
+        }
+        Trap::Exception(Exception::SupervisorEnvCall) => {
+            let x = &mut ctx.x;
+            let (error, value) = sbi::sbi_call(x[17], x[16], [x[10], x[11], x[12], x[13], x[14], x[15]]);
+            x[10] = error as usize;
+            // legacy calls return in a0 alone
+            if x[17] >= 0x10 {
+                x[11] = value;
+            }
+            ctx.mepc += 4;
+        }
+        Trap::Exception(Exception::LoadMisaligned) | Trap::Exception(Exception::StoreMisaligned) => {
+            if let Err(fault) = emulate::emulate_misaligned(ctx, code, mtval) {
+                emulate::redirect_to_s(ctx, fault);
+            }
+        }
+        Trap::Exception(Exception::IllegalInstruction) => {
+            if let Err(fault) = emulate::emulate_illegal(ctx, code, mtval) {
+                emulate::redirect_to_s(ctx, fault);
             }
         }
         _ => {
@@ -0,0 +1,137 @@
+//! SBI calls from the supervisor, v2.0 with the BASE, TIME, IPI, RFENCE,
+//! HSM and SRST extensions and the legacy console. There is one hart.
+use crate::config::*;
+use crate::sbi::{console_getchar, console_putchar, reset, shutdown};
+use core::arch::asm;
+use log::warn;
+use riscv::register::{marchid, mhartid, mie, mimpid, mip, mvendorid};
+
+pub const SBI_SUCCESS: isize = 0;
+pub const SBI_ERR_NOT_SUPPORTED: isize = -2;
+pub const SBI_ERR_INVALID_PARAM: isize = -3;
+pub const SBI_ERR_ALREADY_AVAILABLE: isize = -6;
+
+const LEGACY_CONSOLE_PUTCHAR: usize = 0x01;
+const LEGACY_CONSOLE_GETCHAR: usize = 0x02;
+const EID_BASE: usize = 0x10;
+const EID_TIME: usize = 0x5449_4d45;
+const EID_IPI: usize = 0x0073_5049;
+const EID_RFENCE: usize = 0x5246_4e43;
+const EID_HSM: usize = 0x0048_534d;
+const EID_SRST: usize = 0x5352_5354;
+
+/// version 2.0, major in bits 24..31
+const SPEC_VERSION: usize = 2 << 24;
+/// outside the ids the spec hands out
+const IMPL_ID: usize = 0x7273;
+const IMPL_VERSION: usize = 1;
+
+const HSM_STARTED: usize = 0;
+
+const SRST_SHUTDOWN: usize = 0;
+const SRST_COLD_REBOOT: usize = 1;
+const SRST_WARM_REBOOT: usize = 2;
+const SRST_SYSTEM_FAILURE: usize = 1;
+
+/// Whether `hart_mask` relative to `hart_mask_base` holds this hart,
+/// a base of -1 means all harts
+fn has_this_hart(hart_mask: usize, hart_mask_base: usize) -> bool {
+    if hart_mask_base == usize::MAX {
+        return true;
+    }
+    mhartid::read()
+        .checked_sub(hart_mask_base)
+        .is_some_and(|bit| bit < usize::BITS as usize && hart_mask & 1 << bit != 0)
+}
+
+/// Program the next supervisor timer interrupt and take back the pending one
+pub fn set_timer(stime_value: u64) {
+    unsafe {
+        (MTIMECMP as *mut u64).write_volatile(stime_value);
+        mip::clear_stimer();
+        mie::set_mtimer();
+    }
+}
+
+/// Handle the call `eid`/`fid`, an error and a value go back in a0 and a1.
+/// Legacy calls only return a0, so their value is the error.
+pub fn sbi_call(eid: usize, fid: usize, args: [usize; 6]) -> (isize, usize) {
+    match eid {
+        LEGACY_CONSOLE_PUTCHAR => {
+            console_putchar(args[0] as u8);
+            (SBI_SUCCESS, 0)
+        }
+        LEGACY_CONSOLE_GETCHAR => (console_getchar().map_or(-1, |c| c as isize), 0),
+        EID_BASE => match fid {
+            0 => (SBI_SUCCESS, SPEC_VERSION),
+            1 => (SBI_SUCCESS, IMPL_ID),
+            2 => (SBI_SUCCESS, IMPL_VERSION),
+            3 => {
+                let probed = matches!(
+                    args[0],
+                    LEGACY_CONSOLE_PUTCHAR
+                        | LEGACY_CONSOLE_GETCHAR
+                        | EID_BASE
+                        | EID_TIME
+                        | EID_IPI
+                        | EID_RFENCE
+                        | EID_HSM
+                        | EID_SRST
+                );
+                (SBI_SUCCESS, probed as usize)
+            }
+            4 => (SBI_SUCCESS, mvendorid::read().map_or(0, |id| id.bits())),
+            5 => (SBI_SUCCESS, marchid::read().map_or(0, |id| id.bits())),
+            6 => (SBI_SUCCESS, mimpid::read().map_or(0, |id| id.bits())),
+            _ => (SBI_ERR_NOT_SUPPORTED, 0),
+        },
+        EID_TIME if fid == 0 => {
+            set_timer(args[0] as u64);
+            (SBI_SUCCESS, 0)
+        }
+        EID_IPI if fid == 0 => {
+            if has_this_hart(args[0], args[1]) {
+                unsafe { mip::set_ssoft() };
+            }
+            (SBI_SUCCESS, 0)
+        }
+        // only this hart has to be fenced
+        EID_RFENCE => match fid {
+            0 => {
+                unsafe { asm!("fence.i") };
+                (SBI_SUCCESS, 0)
+            }
+            // remote_sfence_vma and remote_sfence_vma_asid
+            1 | 2 => {
+                unsafe { asm!("sfence.vma") };
+                (SBI_SUCCESS, 0)
+            }
+            // the HFENCE calls, there is no H extension
+            _ => (SBI_ERR_NOT_SUPPORTED, 0),
+        },
+        EID_HSM => match fid {
+            // hart_start, this hart is running and there are no others
+            0 if args[0] == mhartid::read() => (SBI_ERR_ALREADY_AVAILABLE, 0),
+            0 => (SBI_ERR_INVALID_PARAM, 0),
+            // hart_stop, the last hart stopping is the end
+            1 => shutdown(false),
+            2 if args[0] == mhartid::read() => (SBI_SUCCESS, HSM_STARTED),
+            2 => (SBI_ERR_INVALID_PARAM, 0),
+            // hart_suspend, only the retentive default suspend
+            3 if args[0] == 0 => {
+                unsafe { asm!("wfi") };
+                (SBI_SUCCESS, 0)
+            }
+            _ => (SBI_ERR_NOT_SUPPORTED, 0),
+        },
+        EID_SRST if fid == 0 => match args[0] {
+            SRST_SHUTDOWN => shutdown(args[1] == SRST_SYSTEM_FAILURE),
+            SRST_COLD_REBOOT | SRST_WARM_REBOOT => reset(),
+            _ => (SBI_ERR_INVALID_PARAM, 0),
+        },
+        _ => {
+            warn!("[sbi] unsupported call {:#x}/{}", eid, fid);
+            (SBI_ERR_NOT_SUPPORTED, 0)
+        }
+    }
+}
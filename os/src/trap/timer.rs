@@ -15,10 +15,3 @@ pub fn get_time_ms() -> usize {
 pub fn get_time_us() -> usize {
     time::read() / (CLOCK_FREQ / MICRO_PER_SEC)
 }
-
-pub fn set_next_trigger(hartid: usize) {
-    unsafe {
-        let mtimecmp_addr = (MTIMECMP as usize + 8 * hartid) as *mut u64;
-        mtimecmp_addr.write_volatile(time::read() as u64 + TIME_INTERVAL);
-    }
-}
\ No newline at end of file
@@ -92,21 +92,10 @@ impl MmioSerialPort {
     /// Sends a byte on the serial port.
     pub fn send(&mut self, data: u8) {
         let self_data = self.data.load(Ordering::Relaxed);
+        // erasing is up to the line discipline, bytes go out unchanged
         unsafe {
-            match data {
-                8 | 0x7F => {
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(8);
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(b' ');
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(8)
-                }
-                _ => {
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(data);
-                }
-            }
+            wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
+            self_data.write(data);
         }
     }
 
@@ -118,4 +107,14 @@ impl MmioSerialPort {
             self_data.read()
         }
     }
+
+    /// Receives a byte if one is waiting.
+    pub fn try_receive(&mut self) -> Option<u8> {
+        let self_data = self.data.load(Ordering::Relaxed);
+        if self.line_sts().contains(LineStsFlags::INPUT_FULL) {
+            unsafe { Some(self_data.read()) }
+        } else {
+            None
+        }
+    }
 }
@@ -0,0 +1,140 @@
+//! The machine as its device tree describes it. Until `init` reads the
+//! tree, and for anything it lacks, the values are those of QEMU `virt`.
+use crate::config::*;
+use crate::fdt::Fdt;
+use crate::sync::UPSafeCell;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// longest kernel command line kept
+pub const BOOTARGS_MAX: usize = 256;
+
+#[derive(Clone, Copy)]
+pub struct Board {
+    /// end of the RAM the kernel is in
+    pub memory_end: usize,
+    /// frequency of `time`
+    pub clock_freq: usize,
+    pub uart: usize,
+    pub uart_irq: usize,
+    /// the `sifive,test0` device that powers off
+    pub test: usize,
+    /// the `google,goldfish-rtc` wall clock
+    pub rtc: usize,
+    /// base and size
+    pub clint: (usize, usize),
+    pub plic: (usize, usize),
+    /// base and interrupt of the virtio-mmio slots by address
+    pub virtio: [Option<(usize, usize)>; VIRTIO_MMIO_SLOTS],
+}
+
+struct BootArgs {
+    buf: [u8; BOOTARGS_MAX],
+    len: usize,
+}
+
+static BOARD: UPSafeCell<Board> = unsafe {
+    UPSafeCell::new(Board {
+        memory_end: MEMORY_END,
+        clock_freq: CLOCK_FREQ,
+        uart: VIRT_UART0_BASE,
+        uart_irq: VIRT_UART0_IRQ,
+        test: VIRT_TEST_BASE,
+        rtc: VIRT_RTC_BASE,
+        clint: (VIRT_CLINT_BASE, VIRT_CLINT_SIZE),
+        plic: (VIRT_PLIC_BASE, VIRT_PLIC_SIZE),
+        virtio: {
+            let mut virtio = [None; VIRTIO_MMIO_SLOTS];
+            let mut slot = 0;
+            while slot < VIRTIO_MMIO_SLOTS {
+                virtio[slot] = Some((
+                    VIRTIO_MMIO_BASE + slot * VIRTIO_MMIO_SIZE,
+                    VIRTIO_MMIO_IRQ_BASE + slot,
+                ));
+                slot += 1;
+            }
+            virtio
+        },
+    })
+};
+
+static BOOTARGS: UPSafeCell<BootArgs> = unsafe {
+    UPSafeCell::new(BootArgs {
+        buf: [0; BOOTARGS_MAX],
+        len: 0,
+    })
+};
+
+/// What is known of the machine
+pub fn board() -> Board {
+    *BOARD.exclusive_access()
+}
+
+/// `/chosen/bootargs`, empty without a device tree
+pub fn bootargs() -> String {
+    let bootargs = BOOTARGS.exclusive_access();
+    String::from_utf8_lossy(&bootargs.buf[..bootargs.len]).into()
+}
+
+impl Board {
+    /// Register windows to map into the kernel space besides the UART and test pages
+    pub fn mmio(&self) -> Vec<(usize, usize)> {
+        let mut mmio = alloc::vec![self.plic, self.clint, (self.rtc, PAGE_SIZE)];
+        mmio.extend(self.virtio.iter().flatten().map(|&(base, _)| (base, VIRTIO_MMIO_SIZE)));
+        mmio
+    }
+}
+
+/// Read the device tree at `dtb`. It is copied out of, the tree itself sits in
+/// RAM the frame allocator hands out later. Runs before the heap exists.
+pub fn init(dtb: usize) {
+    let Some(fdt) = (unsafe { Fdt::from_addr(dtb) }) else {
+        return;
+    };
+    let mut board = BOARD.exclusive_access();
+    let mut virtio = [None; VIRTIO_MMIO_SLOTS];
+    let mut virtio_count = 0;
+    fdt.walk(|node| {
+        match (node.reg, node.interrupt) {
+            (Some((base, size)), _) if node.device_type == b"memory" => {
+                // the RAM the kernel was loaded into
+                if (base..base + size).contains(&SUPERVISOR_START) {
+                    board.memory_end = base + size;
+                }
+            }
+            (Some((base, _)), Some(irq)) if node.is_compatible("ns16550a") => {
+                board.uart = base;
+                board.uart_irq = irq;
+            }
+            (Some((base, _)), _) if node.is_compatible("sifive,test0") => board.test = base,
+            (Some((base, _)), _) if node.is_compatible("google,goldfish-rtc") => board.rtc = base,
+            (Some(reg), _) if node.is_compatible("riscv,clint0") || node.is_compatible("sifive,clint0") => {
+                board.clint = reg;
+            }
+            (Some(reg), _) if node.is_compatible("riscv,plic0") || node.is_compatible("sifive,plic-1.0.0") => {
+                board.plic = reg;
+            }
+            (Some((base, _)), Some(irq)) if node.is_compatible("virtio,mmio") => {
+                if virtio_count < VIRTIO_MMIO_SLOTS {
+                    virtio[virtio_count] = Some((base, irq));
+                    virtio_count += 1;
+                }
+            }
+            _ => {}
+        }
+        if let Some(freq) = node.timebase_frequency.filter(|_| node.name == "cpus") {
+            board.clock_freq = freq;
+        }
+        if let Some(args) = node.bootargs.filter(|_| node.name == "chosen") {
+            let mut bootargs = BOOTARGS.exclusive_access();
+            let len = args.len().min(BOOTARGS_MAX);
+            bootargs.buf[..len].copy_from_slice(&args[..len]);
+            bootargs.len = len;
+        }
+    });
+    if virtio_count > 0 {
+        // QEMU lists the slots from the top, slot 0 is the lowest address
+        virtio[..virtio_count].sort_unstable_by_key(|slot| slot.map(|(base, _)| base));
+        board.virtio = virtio;
+    }
+}
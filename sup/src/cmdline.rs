@@ -0,0 +1,103 @@
+//! Kernel command line, from `/chosen/bootargs` or else the `BOOTARGS`
+//! the kernel was built with. Words are separated by spaces:
+//!
+//! - `init=<path>`: first program, a bare name is looked up in `bin`
+//! - `loglevel=<n>`: 0 (off) to 5 (trace), or `error`, `warn`, `info`, ...
+//! - `root=<disk>`: disk with the root file system, like `vdb` or `/dev/vdb`
+//! - `tmpfs_size=<size>`: limit of the tmpfs on `/tmp`, like `16M`
+//! - `quiet`: only warnings and errors, no device and app lists
+//! - `selftest`: run the kernel tests instead of init and power off
+use crate::board::bootargs;
+use crate::sync::UPSafeCell;
+use alloc::string::{String, ToString};
+use log::{LevelFilter, info, warn};
+
+#[derive(Clone)]
+pub struct KernelConfig {
+    pub init: String,
+    /// None leaves the level the kernel was built with
+    pub loglevel: Option<LevelFilter>,
+    pub root: Option<String>,
+    /// None leaves `TMPFS_DEFAULT_SIZE`
+    pub tmpfs_size: Option<String>,
+    pub quiet: bool,
+    pub selftest: bool,
+}
+
+impl Default for KernelConfig {
+    fn default() -> Self {
+        Self {
+            init: "initproc".to_string(),
+            loglevel: None,
+            root: None,
+            tmpfs_size: None,
+            quiet: false,
+            selftest: false,
+        }
+    }
+}
+
+static KERNEL_CONFIG: UPSafeCell<Option<KernelConfig>> = unsafe { UPSafeCell::new(None) };
+
+fn parse_loglevel(level: &str) -> Option<LevelFilter> {
+    Some(match level {
+        "0" | "off" => LevelFilter::Off,
+        "1" | "error" => LevelFilter::Error,
+        "2" | "warn" => LevelFilter::Warn,
+        "3" | "info" => LevelFilter::Info,
+        "4" | "debug" => LevelFilter::Debug,
+        "5" | "trace" => LevelFilter::Trace,
+        _ => return None,
+    })
+}
+
+impl KernelConfig {
+    pub fn parse(cmdline: &str) -> Self {
+        let mut config = Self::default();
+        for word in cmdline.split_whitespace() {
+            match word.split_once('=') {
+                Some(("init", path)) => config.init = path.to_string(),
+                Some(("loglevel", level)) => match parse_loglevel(level) {
+                    Some(level) => config.loglevel = Some(level),
+                    None => warn!("[kernel] bad loglevel {}", level),
+                },
+                Some(("root", disk)) => config.root = Some(disk.trim_start_matches("/dev/").to_string()),
+                Some(("tmpfs_size", size)) => config.tmpfs_size = Some(size.to_string()),
+                None if word == "quiet" => config.quiet = true,
+                None if word == "selftest" => config.selftest = true,
+                _ => warn!("[kernel] unknown boot option {}", word),
+            }
+        }
+        config
+    }
+}
+
+/// Parse the command line and apply the log level, needs the heap
+pub fn init() {
+    let mut cmdline = bootargs();
+    if cmdline.is_empty() {
+        cmdline = option_env!("BOOTARGS").unwrap_or("").to_string();
+    }
+    info!("[kernel] command line \"{}\"", cmdline);
+    let config = KernelConfig::parse(&cmdline);
+    // an explicit loglevel wins over quiet
+    match config.loglevel {
+        Some(level) => log::set_max_level(level),
+        None if config.quiet => log::set_max_level(log::max_level().min(LevelFilter::Warn)),
+        None => {}
+    }
+    *KERNEL_CONFIG.exclusive_access() = Some(config);
+}
+
+/// The parsed command line, defaults before `init`
+pub fn kernel_config() -> KernelConfig {
+    KERNEL_CONFIG.exclusive_access().clone().unwrap_or_default()
+}
+
+/// Whether the kernel runs its tests
+pub fn selftest() -> bool {
+    KERNEL_CONFIG
+        .exclusive_access()
+        .as_ref()
+        .is_some_and(|config| config.selftest)
+}
@@ -1,14 +1,17 @@
-pub const MTIME: usize = 0x0200bff8;
-pub const MTIMECMP: usize = 0x02004000;
 pub const TIME_INTERVAL: u64 = 200000;
 
 pub const MACHINE_START: usize = 0x80000000;
 pub const SUPERVISOR_START: usize = 0x80200000;
+/// used without a device tree, like `CLOCK_FREQ` and the `VIRT_*` addresses
 pub const MEMORY_END: usize = 0x88000000;
 
 pub const MACHINE_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const USER_STACK_SIZE: usize = 4096 * 4;
+/// bytes of argv (pointers and strings) accepted by exec
+pub const ARG_MAX: usize = 4096;
+/// nested `#!` interpreters followed by exec
+pub const SHEBANG_MAX_DEPTH: usize = 4;
 pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
@@ -23,19 +26,60 @@ pub const CLOCK_FREQ: usize = 12500000;
 pub const TICKS_PER_SEC: usize = 100;
 pub const MSEC_PER_SEC: usize = 1000;
 pub const MICRO_PER_SEC: usize = 1_000_000;
+pub const NSEC_PER_SEC: usize = 1_000_000_000;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
+/// user images and stacks must stay in the lower half of the sv39 space
+pub const USER_SPACE_END: usize = 1 << (VA_WIDTH_SV39 - 1);
+/// load bias applied to position independent (ET_DYN) user images
+pub const ELF_DYN_BASE: usize = 0x1000_0000;
+/// shared memory objects are mapped from here upwards
+pub const SHM_BASE: usize = 0x20_0000_0000;
+pub const SHM_MAX_SIZE: usize = 0x10_0000;
+
+/// default capacity of a pipe or FIFO
+pub const PIPE_BUFFER_SIZE: usize = PAGE_SIZE;
+/// most F_SETPIPE_SZ may give one
+pub const PIPE_MAX_SIZE: usize = 16 * PAGE_SIZE;
+
+/// connections waiting for `accept` on a unix-domain socket
+pub const UNIX_MAX_BACKLOG: usize = 16;
+/// messages queued on a unix-domain datagram socket
+pub const UNIX_DGRAM_MAX_MSGS: usize = 16;
+
+/// limits of a tmpfs mounted without `size=` or `nr_inodes=`, inodes
+/// live on the kernel heap
+pub const TMPFS_DEFAULT_SIZE: usize = 0x80_0000;
+pub const TMPFS_DEFAULT_INODES: usize = 4096;
+
+pub const MQ_DEFAULT_MAX_MSGS: usize = 10;
+pub const MQ_DEFAULT_MSG_SIZE: usize = 256;
+pub const MQ_MAX_MSGS: usize = 64;
+pub const MQ_MAX_MSG_SIZE: usize = PAGE_SIZE;
+
 pub const VIRT_UART0_BASE: usize = 0x10000000;
+pub const VIRT_UART0_IRQ: usize = 10;
+/// virtio-mmio slots, slot `i` raises interrupt `VIRTIO_MMIO_IRQ_BASE + i`
+pub const VIRTIO_MMIO_BASE: usize = 0x1000_1000;
+pub const VIRTIO_MMIO_SIZE: usize = 0x1000;
+pub const VIRTIO_MMIO_SLOTS: usize = 8;
+pub const VIRTIO_MMIO_IRQ_BASE: usize = 1;
 pub const VIRT_TEST_BASE: usize = 0x100000;
+pub const VIRT_RTC_BASE: usize = 0x101000;
+pub const VIRT_PLIC_BASE: usize = 0x0c00_0000;
+pub const VIRT_PLIC_SIZE: usize = 0x40_0000;
+pub const VIRT_CLINT_BASE: usize = 0x0200_0000;
+pub const VIRT_CLINT_SIZE: usize = 0x1_0000;
+
+/// input a terminal holds before readers take it
+pub const TTY_BUFFER_SIZE: usize = 4096;
+/// longest line in canonical mode, like MAX_CANON
+pub const TTY_MAX_CANON: usize = 255;
 
 pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
     let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
     let bottom = top - KERNEL_STACK_SIZE;
     (bottom, top)
 }
-
-pub const MMIO: &[(usize, usize)] = &[
-    (0x10001000, 0x1000),
-];
\ No newline at end of file
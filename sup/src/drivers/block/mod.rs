@@ -2,28 +2,57 @@ mod virtio_blk;
 
 pub use virtio_blk::VirtIOBlock;
 
+use super::DEVICES;
+use crate::cmdline::kernel_config;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use fs::BlockDevice;
 use lazy_static::*;
 
-pub type BlockDeviceImpl = crate::drivers::block::VirtIOBlock;
-
 lazy_static! {
-    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(BlockDeviceImpl::new());
+    /// The disk with the root file system, `root=` or else the first one
+    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = match kernel_config().root {
+        Some(root) => block_device(&root).unwrap_or_else(|| panic!("No root disk {}", root)),
+        None => block_devices().into_iter().next().expect("No virtio-blk device"),
+    };
+}
+
+/// Every disk in probing order
+pub fn block_devices() -> Vec<Arc<dyn BlockDevice>> {
+    DEVICES
+        .exclusive_access()
+        .iter()
+        .filter_map(|device| device.driver.clone()?.as_block())
+        .collect()
+}
+
+/// The disk called `name`, like `vda`
+pub fn block_device(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    let driver = DEVICES
+        .exclusive_access()
+        .iter()
+        .filter_map(|device| device.driver.clone())
+        .find(|driver| driver.name() == name)?;
+    driver.as_block()
 }
 
+/// Write patterns over the first blocks of the root disk and read them back,
+/// what was there is put back afterwards
 #[allow(unused)]
 pub fn block_device_test() {
     let block_device = BLOCK_DEVICE.clone();
+    let mut saved_buffer = [0u8; 512];
     let mut write_buffer = [0u8; 512];
     let mut read_buffer = [0u8; 512];
     for i in 0..512 {
+        block_device.read_block(i as usize, &mut saved_buffer);
         for byte in write_buffer.iter_mut() {
             *byte = i as u8;
         }
         block_device.write_block(i as usize, &write_buffer);
         block_device.read_block(i as usize, &mut read_buffer);
         assert_eq!(write_buffer, read_buffer);
+        block_device.write_block(i as usize, &saved_buffer);
     }
     println!("block device test passed!");
 }
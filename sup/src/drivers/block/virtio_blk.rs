@@ -1,83 +1,178 @@
 use super::BlockDevice;
-use crate::mm::{
-    FrameTracker, PageTable, PhysAddr, PhysPageNum, StepByOne, VirtAddr, frame_alloc,
-    frame_dealloc, kernel_token,
-};
-use crate::sync::UPSafeCell;
-use alloc::vec::Vec;
-use lazy_static::*;
-use virtio_drivers::{Hal, VirtIOBlk, VirtIOHeader};
+use crate::drivers::virtio::VirtioHal;
+use crate::drivers::{DEVICES, Driver};
+use crate::sync::{UPSafeCell, WaitQueue};
+use crate::task::{block_current_and_run_next, current_task};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use log::error;
+use virtio_drivers::{BlkResp, Error, RespStatus, VirtIOBlk, VirtIOHeader};
 
-#[allow(unused)]
-const VIRTIO0: usize = 0x10001000;
+/// offset of the device config space in the legacy virtio-mmio registers
+const VIRTIO_CONFIG_SPACE: usize = 0x100;
 
-pub struct VirtIOBlock(UPSafeCell<VirtIOBlk<'static, VirtioHal>>);
+/// Requests complete by interrupt, the submitting task sleeps meanwhile.
+/// Before the first task runs there is nobody to switch to, so completions
+/// are polled instead.
+pub struct VirtIOBlock {
+    name: String,
+    /// in 512-byte sectors, the size of a block
+    capacity: usize,
+    virtio_blk: UPSafeCell<VirtIOBlk<'static, VirtioHal>>,
+    requests: UPSafeCell<BlockRequests>,
+}
+
+struct BlockRequests {
+    next_id: usize,
+    /// request ids by virtqueue token, tokens are reused once popped
+    in_flight: BTreeMap<u16, usize>,
+    by_id: BTreeMap<usize, BlockRequest>,
+    /// tasks waiting for free descriptors
+    queue_wait: WaitQueue,
+}
 
-lazy_static! {
-    static ref QUEUE_FRAMES: UPSafeCell<Vec<FrameTracker>> = unsafe { UPSafeCell::new(Vec::new()) };
+struct BlockRequest {
+    /// written by the device, so it must not move
+    resp: Box<BlkResp>,
+    done: bool,
+    wait: WaitQueue,
 }
 
 impl BlockDevice for VirtIOBlock {
+    /// A failed read gives zeros
     fn read_block(&self, block_id: usize, buf: &mut [u8]) {
-        self.0
-            .exclusive_access()
-            .read_block(block_id, buf)
-            .expect("Error when reading VirtIOBlk");
+        let request = unsafe { self.submit_read(block_id, buf) };
+        if !self.wait_for(request) {
+            error!("[kernel] {}: reading block {} failed", self.name, block_id);
+            buf.fill(0);
+        }
     }
     fn write_block(&self, block_id: usize, buf: &[u8]) {
-        self.0
-            .exclusive_access()
-            .write_block(block_id, buf)
-            .expect("Error when writing VirtIOBlk");
+        let request = unsafe { self.submit_write(block_id, buf) };
+        if !self.wait_for(request) {
+            error!("[kernel] {}: writing block {} failed", self.name, block_id);
+        }
     }
-}
-
-impl VirtIOBlock {
-    #[allow(unused)]
-    pub fn new() -> Self {
-        unsafe {
-            Self(UPSafeCell::new(
-                VirtIOBlk::<VirtioHal>::new(&mut *(VIRTIO0 as *mut VirtIOHeader)).unwrap(),
-            ))
+    unsafe fn submit_read(&self, block_id: usize, buf: &mut [u8]) -> usize {
+        self.submit(|blk, resp| unsafe { blk.read_block_nb(block_id, buf, resp) })
+    }
+    unsafe fn submit_write(&self, block_id: usize, buf: &[u8]) -> usize {
+        self.submit(|blk, resp| unsafe { blk.write_block_nb(block_id, buf, resp) })
+    }
+    fn wait_for(&self, request: usize) -> bool {
+        loop {
+            let mut requests = self.requests.exclusive_access();
+            let pending = requests.by_id.get_mut(&request).unwrap();
+            if pending.done {
+                let status = requests.by_id.remove(&request).unwrap().resp.status();
+                return status == RespStatus::Ok;
+            }
+            if current_task().is_none() {
+                drop(requests);
+                self.handle_irq();
+                continue;
+            }
+            // not woken by signals, the device owns the buffer until done
+            pending.wait.add_current();
+            drop(requests);
+            block_current_and_run_next();
         }
     }
+    fn num_blocks(&self) -> Option<usize> {
+        Some(self.capacity)
+    }
 }
 
-pub struct VirtioHal;
-
-impl Hal for VirtioHal {
-    fn dma_alloc(pages: usize) -> usize {
-        let mut ppn_base = PhysPageNum(0);
-        for i in 0..pages {
-            let frame = frame_alloc().unwrap();
-            if i == 0 {
-                ppn_base = frame.ppn;
+impl Driver for VirtIOBlock {
+    /// Disks are named `vda`, `vdb`, ... in probing order
+    fn probe(header: &'static mut VirtIOHeader) -> Option<Arc<Self>> {
+        let disks = DEVICES
+            .exclusive_access()
+            .iter()
+            .filter_map(|device| device.driver.clone())
+            .filter(|driver| driver.clone().as_block().is_some())
+            .count();
+        // the crate keeps the capacity to itself, it is the first field of
+        // the config space, read in halves as legacy devices want
+        let config = header as *mut VirtIOHeader as usize + VIRTIO_CONFIG_SPACE;
+        let capacity = unsafe {
+            let low = (config as *const u32).read_volatile() as usize;
+            let high = ((config + 4) as *const u32).read_volatile() as usize;
+            high << 32 | low
+        };
+        let virtio_blk = VirtIOBlk::<VirtioHal>::new(header).ok()?;
+        Some(Arc::new(unsafe {
+            Self {
+                name: format!("vd{}", (b'a' + disks as u8) as char),
+                capacity,
+                virtio_blk: UPSafeCell::new(virtio_blk),
+                requests: UPSafeCell::new(BlockRequests {
+                    next_id: 0,
+                    in_flight: BTreeMap::new(),
+                    by_id: BTreeMap::new(),
+                    queue_wait: WaitQueue::new(),
+                }),
             }
-            assert_eq!(frame.ppn.0, ppn_base.0 + i);
-            QUEUE_FRAMES.exclusive_access().push(frame);
-        }
-        let pa: PhysAddr = ppn_base.into();
-        pa.0
+        }))
     }
-
-    fn dma_dealloc(pa: usize, pages: usize) -> i32 {
-        let pa = PhysAddr::from(pa);
-        let mut ppn_base: PhysPageNum = pa.into();
-        for _ in 0..pages {
-            frame_dealloc(ppn_base);
-            ppn_base.step();
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn handle_irq(&self) {
+        let mut blk = self.virtio_blk.exclusive_access();
+        blk.ack_interrupt();
+        let mut requests = self.requests.exclusive_access();
+        while let Ok(token) = blk.pop_used() {
+            let Some(id) = requests.in_flight.remove(&token) else {
+                continue;
+            };
+            let request = requests.by_id.get_mut(&id).unwrap();
+            request.done = true;
+            request.wait.wake_all();
         }
-        0
+        requests.queue_wait.wake_all();
     }
-
-    fn phys_to_virt(addr: usize) -> usize {
-        addr
+    fn as_block(self: Arc<Self>) -> Option<Arc<dyn BlockDevice>> {
+        Some(self)
     }
+}
 
-    fn virt_to_phys(vaddr: usize) -> usize {
-        PageTable::from_token(kernel_token())
-            .translate_va(VirtAddr::from(vaddr))
-            .unwrap()
-            .0
+impl VirtIOBlock {
+    /// Put a request on the virtqueue, waiting for room if it is full
+    fn submit(
+        &self,
+        mut start: impl FnMut(&mut VirtIOBlk<'static, VirtioHal>, &mut BlkResp) -> Result<u16, Error>,
+    ) -> usize {
+        let mut resp = Box::new(BlkResp::default());
+        loop {
+            // the device is let go before waiting, the interrupt handler takes it
+            let result = start(&mut self.virtio_blk.exclusive_access(), &mut resp);
+            match result {
+                Ok(token) => {
+                    let mut requests = self.requests.exclusive_access();
+                    let id = requests.next_id;
+                    requests.next_id += 1;
+                    requests.in_flight.insert(token, id);
+                    requests.by_id.insert(
+                        id,
+                        BlockRequest {
+                            resp,
+                            done: false,
+                            wait: WaitQueue::new(),
+                        },
+                    );
+                    return id;
+                }
+                Err(Error::BufferTooSmall) if current_task().is_some() => {
+                    self.requests.exclusive_access().queue_wait.add_current();
+                    block_current_and_run_next();
+                }
+                Err(Error::BufferTooSmall) => self.handle_irq(),
+                Err(err) => panic!("Error when submitting to VirtIOBlk: {:?}", err),
+            }
+        }
     }
 }
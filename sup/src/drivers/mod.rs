@@ -1,3 +1,126 @@
 pub mod block;
+pub mod plic;
+mod rng;
+pub mod rtc;
+pub mod serial;
+mod virtio;
 
 pub use block::BLOCK_DEVICE;
+
+use crate::board::board;
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use fs::BlockDevice;
+use lazy_static::*;
+use log::warn;
+use plic::{IntrTargetPriority, plic};
+use virtio_drivers::{DeviceType, VirtIOHeader};
+
+/// A driver bound to one device of the table
+pub trait Driver: Send + Sync {
+    /// Take the virtio device behind `header`, None if it does not work
+    fn probe(header: &'static mut VirtIOHeader) -> Option<Arc<Self>>
+    where
+        Self: Sized;
+    /// Name of the device, like `vda`
+    fn name(&self) -> &str;
+    /// Interrupt from the device
+    fn handle_irq(&self);
+    /// The device as a disk, if it is one
+    fn as_block(self: Arc<Self>) -> Option<Arc<dyn BlockDevice>> {
+        None
+    }
+    /// Fill `buf` with entropy, the bytes written, 0 if it is no entropy source
+    fn read_entropy(&self, _buf: &mut [u8]) -> usize {
+        0
+    }
+}
+
+/// A virtio-mmio device found by probing
+pub struct Device {
+    pub base: usize,
+    pub irq: usize,
+    pub device_type: DeviceType,
+    /// None when no driver handles this kind of device
+    pub driver: Option<Arc<dyn Driver>>,
+}
+
+lazy_static! {
+    /// Devices in slot order
+    pub static ref DEVICES: UPSafeCell<Vec<Device>> = unsafe { UPSafeCell::new(Vec::new()) };
+}
+
+/// Find the devices and route their interrupts
+pub fn init() {
+    virtio::probe_virtio();
+    init_irq();
+}
+
+/// Fill `buf` from the first entropy source that gives anything, the bytes written
+pub fn read_entropy(buf: &mut [u8]) -> usize {
+    let drivers = DEVICES
+        .exclusive_access()
+        .iter()
+        .filter_map(|device| device.driver.clone())
+        .collect::<Vec<_>>();
+    drivers
+        .iter()
+        .map(|driver| driver.read_entropy(buf))
+        .find(|&read| read > 0)
+        .unwrap_or(0)
+}
+
+/// List the device table
+pub fn list_devices() {
+    println!("/**** DEVICES ****");
+    for device in DEVICES.exclusive_access().iter() {
+        match &device.driver {
+            Some(driver) => {
+                println!("{:#x} irq {}: {:?} {}", device.base, device.irq, device.device_type, driver.name());
+            }
+            None => {
+                println!("{:#x} irq {}: {:?} (no driver)", device.base, device.irq, device.device_type);
+            }
+        }
+    }
+    println!("*****************/");
+}
+
+/// Route the interrupts of the UART and of driven devices to supervisor mode of hart 0
+fn init_irq() {
+    let mut plic = plic();
+    plic.set_threshold(0, IntrTargetPriority::Supervisor, 0);
+    let driven = DEVICES
+        .exclusive_access()
+        .iter()
+        .filter(|device| device.driver.is_some())
+        .map(|device| device.irq)
+        .collect::<Vec<_>>();
+    for intr_source_id in driven.into_iter().chain([board().uart_irq]) {
+        plic.enable(0, IntrTargetPriority::Supervisor, intr_source_id);
+        plic.set_priority(intr_source_id, 1);
+    }
+}
+
+/// Handle one pending external interrupt
+pub fn irq_handler() {
+    let mut plic = plic();
+    let intr_source_id = plic.claim(0, IntrTargetPriority::Supervisor);
+    match intr_source_id as usize {
+        0 => return,
+        irq if irq == board().uart_irq => serial::handle_irq(),
+        irq => {
+            let driver = DEVICES
+                .exclusive_access()
+                .iter()
+                .find(|device| device.irq == irq)
+                .and_then(|device| device.driver.clone());
+            match driver {
+                Some(driver) => driver.handle_irq(),
+                None => warn!("[kernel] unexpected external interrupt {}", irq),
+            }
+        }
+    }
+    plic.complete(0, IntrTargetPriority::Supervisor, intr_source_id);
+}
@@ -0,0 +1,158 @@
+//! virtio entropy device. virtio-drivers at the pinned rev has no driver for
+//! it, so its one virtqueue is driven here through the legacy virtio-mmio
+//! registers, the transport the crate speaks, with DMA memory from `VirtioHal`.
+use super::Driver;
+use super::virtio::VirtioHal;
+use crate::config::PAGE_SIZE;
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use core::sync::atomic::{Ordering, fence};
+use virtio_drivers::{Hal, VirtIOHeader};
+
+// legacy virtio-mmio registers
+const VERSION: usize = 0x004;
+const GUEST_FEATURES: usize = 0x020;
+const GUEST_FEATURES_SEL: usize = 0x024;
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+const QUEUE_ALIGN: usize = 0x03c;
+const QUEUE_PFN: usize = 0x040;
+const QUEUE_NOTIFY: usize = 0x050;
+const INTERRUPT_STATUS: usize = 0x060;
+const INTERRUPT_ACK: usize = 0x064;
+const STATUS: usize = 0x070;
+
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+
+const DESC_F_WRITE: u16 = 2;
+const AVAIL_F_NO_INTERRUPT: u16 = 1;
+
+/// Only one request is ever in flight, a queue size is a power of two
+const QUEUE_SIZE: usize = 4;
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct AvailRing {
+    flags: u16,
+    idx: u16,
+    ring: [u16; QUEUE_SIZE],
+    used_event: u16,
+}
+
+#[repr(C)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct UsedRing {
+    flags: u16,
+    idx: u16,
+    ring: [UsedElem; QUEUE_SIZE],
+    avail_event: u16,
+}
+
+/// Requests are polled, the device answers as soon as the host has the bytes.
+/// The queue takes three pages: descriptors and the available ring, the used
+/// ring at the next page as legacy alignment wants, and the buffer the
+/// device fills.
+pub struct VirtIORng {
+    regs: usize,
+    queue_pa: usize,
+    /// index of the next entry of the available ring
+    next_avail: UPSafeCell<u16>,
+}
+
+impl VirtIORng {
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { ((self.regs + offset) as *const u32).read_volatile() }
+    }
+    fn write_reg(&self, offset: usize, value: u32) {
+        unsafe { ((self.regs + offset) as *mut u32).write_volatile(value) }
+    }
+    fn page(&self, index: usize) -> usize {
+        VirtioHal::phys_to_virt(self.queue_pa + index * PAGE_SIZE)
+    }
+}
+
+impl Driver for VirtIORng {
+    fn probe(header: &'static mut VirtIOHeader) -> Option<Arc<Self>> {
+        let mut rng = Self {
+            regs: header as *mut VirtIOHeader as usize,
+            queue_pa: 0,
+            next_avail: unsafe { UPSafeCell::new(0) },
+        };
+        // the modern interface is left alone, as the crate does
+        if rng.read_reg(VERSION) != 1 {
+            return None;
+        }
+        rng.write_reg(STATUS, 0);
+        rng.write_reg(STATUS, STATUS_ACKNOWLEDGE);
+        rng.write_reg(STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+        // the device has no features of its own
+        rng.write_reg(GUEST_FEATURES_SEL, 0);
+        rng.write_reg(GUEST_FEATURES, 0);
+        rng.write_reg(GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+        rng.write_reg(QUEUE_SEL, 0);
+        if (rng.read_reg(QUEUE_NUM_MAX) as usize) < QUEUE_SIZE {
+            return None;
+        }
+        rng.write_reg(QUEUE_NUM, QUEUE_SIZE as u32);
+        rng.write_reg(QUEUE_ALIGN, PAGE_SIZE as u32);
+        rng.queue_pa = VirtioHal::dma_alloc(3);
+        let avail = (rng.page(0) + QUEUE_SIZE * size_of::<Descriptor>()) as *mut AvailRing;
+        unsafe { (&raw mut (*avail).flags).write_volatile(AVAIL_F_NO_INTERRUPT) };
+        rng.write_reg(QUEUE_PFN, (rng.queue_pa / PAGE_SIZE) as u32);
+        rng.write_reg(STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_DRIVER_OK);
+        Some(Arc::new(rng))
+    }
+    fn name(&self) -> &str {
+        "hwrng"
+    }
+    fn handle_irq(&self) {
+        let status = self.read_reg(INTERRUPT_STATUS);
+        self.write_reg(INTERRUPT_ACK, status);
+    }
+    fn read_entropy(&self, buf: &mut [u8]) -> usize {
+        let mut next_avail = self.next_avail.exclusive_access();
+        let len = buf.len().min(PAGE_SIZE);
+        let slot = *next_avail as usize % QUEUE_SIZE;
+        let desc = self.page(0) as *mut Descriptor;
+        let avail = (self.page(0) + QUEUE_SIZE * size_of::<Descriptor>()) as *mut AvailRing;
+        let used = self.page(1) as *const UsedRing;
+        let data = self.page(2) as *const u8;
+        let written = unsafe {
+            desc.write_volatile(Descriptor {
+                addr: (self.queue_pa + 2 * PAGE_SIZE) as u64,
+                len: len as u32,
+                flags: DESC_F_WRITE,
+                next: 0,
+            });
+            (&raw mut (*avail).ring[slot]).write_volatile(0);
+            fence(Ordering::SeqCst);
+            *next_avail = next_avail.wrapping_add(1);
+            (&raw mut (*avail).idx).write_volatile(*next_avail);
+            fence(Ordering::SeqCst);
+            self.write_reg(QUEUE_NOTIFY, 0);
+            while (&raw const (*used).idx).read_volatile() != *next_avail {
+                core::hint::spin_loop();
+            }
+            fence(Ordering::SeqCst);
+            ((&raw const (*used).ring[slot].len).read_volatile() as usize).min(len)
+        };
+        buf[..written].copy_from_slice(unsafe { core::slice::from_raw_parts(data, written) });
+        written
+    }
+}
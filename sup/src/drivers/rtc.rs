@@ -0,0 +1,15 @@
+//! Goldfish RTC of QEMU `virt`, the wall clock
+use crate::board::board;
+
+const TIME_LOW: usize = 0x00;
+const TIME_HIGH: usize = 0x04;
+
+/// Nanoseconds since 1970-01-01 UTC. Reading the low half latches the high one.
+pub fn rtc_time_ns() -> usize {
+    let base = board().rtc;
+    unsafe {
+        let low = ((base + TIME_LOW) as *const u32).read_volatile() as usize;
+        let high = ((base + TIME_HIGH) as *const u32).read_volatile() as usize;
+        high << 32 | low
+    }
+}
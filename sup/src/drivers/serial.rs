@@ -0,0 +1,13 @@
+//! Console input, fed to the console terminal by the UART receive interrupt
+use crate::fs::{CONSOLE_TTY, tty_receive};
+use crate::sbi::{try_scan, write_bytes};
+use alloc::vec::Vec;
+
+/// Pass everything the UART received to the line discipline and print the echo
+pub fn handle_irq() {
+    let mut echo = Vec::new();
+    while let Some(c) = try_scan() {
+        tty_receive(&CONSOLE_TTY, c, &mut echo);
+    }
+    write_bytes(&echo);
+}
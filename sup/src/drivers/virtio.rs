@@ -0,0 +1,91 @@
+//! Probing of the virtio-mmio slots and the DMA glue shared by virtio drivers
+use super::block::VirtIOBlock;
+use super::rng::VirtIORng;
+use super::{DEVICES, Device, Driver};
+use crate::board::board;
+use crate::mm::{
+    FrameTracker, PageTable, PhysAddr, PhysPageNum, StepByOne, VirtAddr, frame_alloc,
+    frame_dealloc, kernel_token,
+};
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+use virtio_drivers::{DeviceType, Hal, VirtIOHeader};
+
+lazy_static! {
+    static ref QUEUE_FRAMES: UPSafeCell<Vec<FrameTracker>> = unsafe { UPSafeCell::new(Vec::new()) };
+}
+
+type ProbeFn = fn(&'static mut VirtIOHeader) -> Option<Arc<dyn Driver>>;
+
+fn probe<D: Driver + 'static>(header: &'static mut VirtIOHeader) -> Option<Arc<dyn Driver>> {
+    D::probe(header).map(|driver| driver as Arc<dyn Driver>)
+}
+
+/// Drivers by the device ID they handle, a new driver only needs a line here
+const VIRTIO_DRIVERS: &[(DeviceType, ProbeFn)] = &[
+    (DeviceType::Block, probe::<VirtIOBlock>),
+    (DeviceType::EntropySource, probe::<VirtIORng>),
+];
+
+/// Walk every virtio-mmio slot of the board and put what answers into the device table
+pub fn probe_virtio() {
+    for (base, irq) in board().virtio.into_iter().flatten() {
+        let header = unsafe { &mut *(base as *mut VirtIOHeader) };
+        // an empty slot has device ID 0
+        if !header.verify() || header.device_type() == DeviceType::Invalid {
+            continue;
+        }
+        let device_type = header.device_type();
+        let driver = VIRTIO_DRIVERS
+            .iter()
+            .find(|(handled, _)| *handled == device_type)
+            .and_then(|(_, probe)| probe(header));
+        DEVICES.exclusive_access().push(Device {
+            base,
+            irq,
+            device_type,
+            driver,
+        });
+    }
+}
+
+pub struct VirtioHal;
+
+impl Hal for VirtioHal {
+    fn dma_alloc(pages: usize) -> usize {
+        let mut ppn_base = PhysPageNum(0);
+        for i in 0..pages {
+            let frame = frame_alloc().unwrap();
+            if i == 0 {
+                ppn_base = frame.ppn;
+            }
+            assert_eq!(frame.ppn.0, ppn_base.0 + i);
+            QUEUE_FRAMES.exclusive_access().push(frame);
+        }
+        let pa: PhysAddr = ppn_base.into();
+        pa.0
+    }
+
+    fn dma_dealloc(pa: usize, pages: usize) -> i32 {
+        let pa = PhysAddr::from(pa);
+        let mut ppn_base: PhysPageNum = pa.into();
+        for _ in 0..pages {
+            frame_dealloc(ppn_base);
+            ppn_base.step();
+        }
+        0
+    }
+
+    fn phys_to_virt(addr: usize) -> usize {
+        addr
+    }
+
+    fn virt_to_phys(vaddr: usize) -> usize {
+        PageTable::from_token(kernel_token())
+            .translate_va(VirtAddr::from(vaddr))
+            .unwrap()
+            .0
+    }
+}
@@ -0,0 +1,153 @@
+//! Reading of the flattened device tree the machine stage passes in a1.
+//! Nothing is allocated, so it works before the heap is up.
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+const FDT_MAX_DEPTH: usize = 16;
+
+/// A node with the properties the kernel looks at
+#[derive(Clone, Copy, Default)]
+pub struct Node<'a> {
+    /// name with the unit address, like `memory@80000000`
+    pub name: &'a str,
+    /// NUL separated list
+    pub compatible: &'a [u8],
+    pub device_type: &'a [u8],
+    /// first (address, size) pair of `reg`
+    pub reg: Option<(usize, usize)>,
+    /// first cell of `interrupts`
+    pub interrupt: Option<usize>,
+    pub timebase_frequency: Option<usize>,
+    /// without the NUL
+    pub bootargs: Option<&'a [u8]>,
+    /// cells of `reg` in the children
+    address_cells: usize,
+    size_cells: usize,
+}
+
+impl Node<'_> {
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.compatible
+            .split(|&byte| byte == 0)
+            .any(|name| name == compatible.as_bytes())
+    }
+}
+
+pub struct Fdt<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+}
+
+fn be32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Big endian number of `cells` 32-bit cells
+fn read_cells(bytes: &[u8], cells: usize) -> Option<usize> {
+    (0..cells).try_fold(0usize, |value, i| Some(value << 32 | be32(bytes, i * 4)? as usize))
+}
+
+fn cstr(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+impl Fdt<'static> {
+    /// The tree at physical address `addr`, None if there is no valid header
+    ///
+    /// # Safety
+    /// `addr` must be readable and the tree must stay intact while in use.
+    pub unsafe fn from_addr(addr: usize) -> Option<Self> {
+        if addr == 0 || addr % 4 != 0 {
+            return None;
+        }
+        let header = unsafe { core::slice::from_raw_parts(addr as *const u8, 40) };
+        if be32(header, 0)? != FDT_MAGIC {
+            return None;
+        }
+        let total_size = be32(header, 4)? as usize;
+        let blob = unsafe { core::slice::from_raw_parts(addr as *const u8, total_size) };
+        let structs_offset = be32(header, 8)? as usize;
+        let strings_offset = be32(header, 12)? as usize;
+        let strings_size = be32(header, 32)? as usize;
+        let structs_size = be32(header, 36)? as usize;
+        Some(Self {
+            structs: blob.get(structs_offset..structs_offset + structs_size)?,
+            strings: blob.get(strings_offset..strings_offset + strings_size)?,
+        })
+    }
+}
+
+impl<'a> Fdt<'a> {
+    /// Call `f` on every node after its properties, children come first
+    pub fn walk(&self, mut f: impl FnMut(&Node<'a>)) {
+        self.try_walk(&mut f);
+    }
+    fn try_walk(&self, f: &mut impl FnMut(&Node<'a>)) -> Option<()> {
+        let structs = self.structs;
+        let mut stack = [Node::default(); FDT_MAX_DEPTH];
+        let mut depth = 0;
+        let mut offset = 0;
+        loop {
+            let token = be32(structs, offset)?;
+            offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = cstr(structs.get(offset..)?);
+                    offset = (offset + name.len() + 1).next_multiple_of(4);
+                    if depth == FDT_MAX_DEPTH {
+                        return None;
+                    }
+                    stack[depth] = Node {
+                        name: core::str::from_utf8(name).unwrap_or(""),
+                        address_cells: 2,
+                        size_cells: 1,
+                        ..Node::default()
+                    };
+                    depth += 1;
+                }
+                FDT_END_NODE => {
+                    depth = depth.checked_sub(1)?;
+                    f(&stack[depth]);
+                }
+                FDT_PROP => {
+                    let len = be32(structs, offset)? as usize;
+                    let name = cstr(self.strings.get(be32(structs, offset + 4)? as usize..)?);
+                    let value = structs.get(offset + 8..offset + 8 + len)?;
+                    offset = (offset + 8 + len).next_multiple_of(4);
+                    if depth == 0 {
+                        continue;
+                    }
+                    // `reg` is laid out by the cells of the parent
+                    let (address_cells, size_cells) = match depth {
+                        1 => (2, 1),
+                        _ => (stack[depth - 2].address_cells, stack[depth - 2].size_cells),
+                    };
+                    let node = &mut stack[depth - 1];
+                    match name {
+                        b"compatible" => node.compatible = value,
+                        b"device_type" => node.device_type = cstr(value),
+                        b"reg" => {
+                            node.reg = read_cells(value, address_cells).zip(read_cells(
+                                value.get(address_cells * 4..)?,
+                                size_cells,
+                            ))
+                        }
+                        b"interrupts" => node.interrupt = read_cells(value, 1),
+                        b"timebase-frequency" => node.timebase_frequency = read_cells(value, len / 4),
+                        b"bootargs" => node.bootargs = Some(cstr(value)),
+                        b"#address-cells" => node.address_cells = read_cells(value, 1)?,
+                        b"#size-cells" => node.size_cells = read_cells(value, 1)?,
+                        _ => {}
+                    }
+                }
+                FDT_NOP => {}
+                // FDT_END
+                _ => return Some(()),
+            }
+        }
+    }
+}
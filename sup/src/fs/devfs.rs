@@ -0,0 +1,203 @@
+//! Device files under `/dev`. Nothing is stored on disk, a name maps straight
+//! to the `File` that serves it.
+use super::{
+    CONSOLE_TTY, ENOSPC, File, OpenFlags, PollEvents, RandomDevice, Stdin, Stdout, open_ptmx,
+    open_pts, tty_ioctl, tty_read,
+};
+use crate::drivers::block::block_device;
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use alloc::sync::Arc;
+use alloc::vec;
+use fs::{BLOCK_SZ, BlockDevice};
+
+/// Open `/dev/<name>`, None if there is no such device
+pub fn open_device(name: &str, flags: OpenFlags) -> Option<Arc<dyn File + Send + Sync>> {
+    let (readable, writable) = flags.read_write();
+    let nonblock = flags.contains(OpenFlags::NONBLOCK);
+    if let Some(index) = name.strip_prefix("pts/") {
+        return open_pts(index.parse().ok()?, nonblock).map(|pts| pts as Arc<dyn File + Send + Sync>);
+    }
+    let device: Arc<dyn File + Send + Sync> = match name {
+        "null" => Arc::new(Null),
+        "zero" => Arc::new(Zero),
+        "full" => Arc::new(Full),
+        "console" => Arc::new(Console { nonblock }),
+        "ptmx" => open_ptmx(nonblock),
+        "random" | "urandom" => Arc::new(RandomDevice),
+        disk => Arc::new(DiskFile::new(block_device(disk)?, readable, writable)),
+    };
+    Some(device)
+}
+
+/// Reads give end of file, writes are thrown away
+pub struct Null;
+
+/// Reads give zeros, writes are thrown away
+pub struct Zero;
+
+/// Reads give zeros, writes fail as on a full disk
+pub struct Full;
+
+/// The UART terminal, what the first tasks get as fds 0 and 1
+pub struct Console {
+    nonblock: bool,
+}
+
+fn fill_zero(mut buf: UserBuffer) -> isize {
+    for buffer in buf.buffers.iter_mut() {
+        buffer.fill(0);
+    }
+    buf.len() as isize
+}
+
+impl File for Null {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, _buf: UserBuffer) -> isize {
+        0
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        buf.len() as isize
+    }
+}
+
+impl File for Zero {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> isize {
+        fill_zero(buf)
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        buf.len() as isize
+    }
+}
+
+impl File for Full {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> isize {
+        fill_zero(buf)
+    }
+    fn write(&self, _buf: UserBuffer) -> isize {
+        ENOSPC
+    }
+}
+
+impl File for Console {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> isize {
+        tty_read(&CONSOLE_TTY, buf, self.nonblock)
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        Stdout.write(buf)
+    }
+    fn poll(&self) -> PollEvents {
+        Stdin.poll() | PollEvents::OUT
+    }
+    fn add_poll_waiter(&self) {
+        Stdin.add_poll_waiter();
+    }
+    fn ioctl(&self, request: usize, arg: usize) -> isize {
+        tty_ioctl(&CONSOLE_TTY, request, arg)
+    }
+}
+
+/// A whole disk as one file. It goes around the block cache of a file
+/// system on the disk, which does not see what is written here.
+pub struct DiskFile {
+    readable: bool,
+    writable: bool,
+    disk: Arc<dyn BlockDevice>,
+    offset: UPSafeCell<usize>,
+}
+
+impl DiskFile {
+    fn new(disk: Arc<dyn BlockDevice>, readable: bool, writable: bool) -> Self {
+        Self {
+            readable,
+            writable,
+            disk,
+            offset: unsafe { UPSafeCell::new(0) },
+        }
+    }
+    /// Bytes on the disk, as many as can be addressed if it cannot tell
+    fn size(&self) -> usize {
+        self.disk
+            .num_blocks()
+            .map_or(usize::MAX, |blocks| blocks * BLOCK_SZ)
+    }
+}
+
+impl File for DiskFile {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, mut buf: UserBuffer) -> isize {
+        // block I/O sleeps, so the offset is not held across it
+        let start = *self.offset.exclusive_access();
+        let size = self.size();
+        // the device takes the buffer by physical address, the heap is contiguous
+        let mut block = vec![0u8; BLOCK_SZ];
+        let mut pos = start;
+        for buffer in buf.buffers.iter_mut() {
+            let mut done = 0;
+            while done < buffer.len() && pos < size {
+                let in_block = pos % BLOCK_SZ;
+                let len = (BLOCK_SZ - in_block).min(buffer.len() - done).min(size - pos);
+                self.disk.read_block(pos / BLOCK_SZ, &mut block);
+                buffer[done..done + len].copy_from_slice(&block[in_block..in_block + len]);
+                done += len;
+                pos += len;
+            }
+        }
+        *self.offset.exclusive_access() = pos;
+        (pos - start) as isize
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        let start = *self.offset.exclusive_access();
+        let size = self.size();
+        if start >= size && buf.len() > 0 {
+            return ENOSPC;
+        }
+        let mut block = vec![0u8; BLOCK_SZ];
+        let mut pos = start;
+        for buffer in buf.buffers.iter() {
+            let mut done = 0;
+            while done < buffer.len() && pos < size {
+                let in_block = pos % BLOCK_SZ;
+                let len = (BLOCK_SZ - in_block).min(buffer.len() - done).min(size - pos);
+                // a part of a block keeps the rest of it
+                if len < BLOCK_SZ {
+                    self.disk.read_block(pos / BLOCK_SZ, &mut block);
+                }
+                block[in_block..in_block + len].copy_from_slice(&buffer[done..done + len]);
+                self.disk.write_block(pos / BLOCK_SZ, &block);
+                done += len;
+                pos += len;
+            }
+        }
+        *self.offset.exclusive_access() = pos;
+        (pos - start) as isize
+    }
+}
@@ -0,0 +1,143 @@
+//! easy-fs behind the VFS
+use super::vfs::{FileSystemType, InodeType, Stat, VfsInode};
+use super::{EINVAL, ENOENT};
+use crate::drivers::block::block_device;
+use crate::sync::SleepLock;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use fs::{BLOCK_SZ, BlockDevice, FileSystem, Inode};
+
+/// Held around every use of easy-fs. Block I/O sleeps with its spin locks
+/// held, so other tasks have to sleep here rather than spin on those.
+static FS_LOCK: SleepLock = SleepLock::new();
+
+pub struct EasyFsType;
+
+impl FileSystemType for EasyFsType {
+    fn name(&self) -> &'static str {
+        "easyfs"
+    }
+    /// `source` is a disk, like `vdb` or `/dev/vdb`, there are no options
+    fn mount(&self, source: &str, _data: &str) -> Result<Arc<dyn VfsInode>, isize> {
+        let disk = block_device(source.trim_start_matches("/dev/")).ok_or(ENOENT)?;
+        open_disk(disk).ok_or(EINVAL)
+    }
+}
+
+/// The root directory of the easy-fs on `disk`, None if there is none
+pub fn open_disk(disk: Arc<dyn BlockDevice>) -> Option<Arc<dyn VfsInode>> {
+    let _fs = FS_LOCK.lock();
+    let fs = FileSystem::try_open(disk.clone())?;
+    Some(Arc::new(EasyFsInode {
+        inode: Arc::new(FileSystem::root_inode(&fs)),
+        dev: Arc::as_ptr(&disk) as *const () as usize,
+    }))
+}
+
+pub struct EasyFsInode {
+    inode: Arc<Inode>,
+    /// address of the disk, every mount of it shares the inodes
+    dev: usize,
+}
+
+impl EasyFsInode {
+    fn wrap(&self, inode: Arc<Inode>) -> Arc<dyn VfsInode> {
+        Arc::new(Self { inode, dev: self.dev })
+    }
+}
+
+impl VfsInode for EasyFsInode {
+    fn lookup(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        let _fs = FS_LOCK.lock();
+        let inode = self.inode.find(name)?;
+        Some(self.wrap(inode))
+    }
+    fn create(&self, name: &str, type_: InodeType) -> Option<Arc<dyn VfsInode>> {
+        let _fs = FS_LOCK.lock();
+        let inode = match type_ {
+            InodeType::File => self.inode.create(name),
+            InodeType::Directory => self.inode.mkdir(name),
+            InodeType::Fifo => self.inode.mkfifo(name),
+            InodeType::Socket => self.inode.mksock(name),
+        }?;
+        Some(self.wrap(inode))
+    }
+    fn readdir(&self) -> Vec<String> {
+        let _fs = FS_LOCK.lock();
+        let mut names = self.inode.names();
+        names.retain(|name| name != "." && name != "..");
+        names
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = FS_LOCK.lock();
+        self.inode.read_at(offset, buf)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let _fs = FS_LOCK.lock();
+        self.inode.write_at(offset, buf)
+    }
+    fn stat(&self) -> Stat {
+        let _fs = FS_LOCK.lock();
+        let type_ = if self.inode.is_dir() {
+            InodeType::Directory
+        } else if self.inode.is_fifo() {
+            InodeType::Fifo
+        } else if self.inode.is_socket() {
+            InodeType::Socket
+        } else {
+            InodeType::File
+        };
+        let (block_id, block_offset) = self.inode.disk_position();
+        Stat {
+            dev: self.dev,
+            ino: block_id * BLOCK_SZ + block_offset,
+            type_,
+            size: self.inode.size(),
+        }
+    }
+    fn unlink(&self, name: &str) -> bool {
+        let _fs = FS_LOCK.lock();
+        self.inode.remove(name)
+    }
+    fn truncate(&self) {
+        let _fs = FS_LOCK.lock();
+        self.inode.clear();
+    }
+    fn rename(&self, old_name: &str, new_parent: &Arc<dyn VfsInode>, new_name: &str) -> bool {
+        let Some(new_parent) = (new_parent.as_ref() as &dyn Any).downcast_ref::<EasyFsInode>()
+        else {
+            return false;
+        };
+        if new_parent.dev != self.dev {
+            return false;
+        }
+        let _fs = FS_LOCK.lock();
+        if !new_parent.inode.is_dir() {
+            return false;
+        }
+        let Some(inode) = self.inode.find(old_name) else {
+            return false;
+        };
+        if self.inode.disk_position() == new_parent.inode.disk_position() {
+            return self.inode._rename(old_name, new_name);
+        }
+        // a directory moved below itself would be cut off from the root,
+        // so look for it on the way up from the new parent
+        if inode.is_dir() {
+            let mut ancestor = Some(new_parent.inode.clone());
+            while let Some(dir) = ancestor {
+                if dir.disk_position() == inode.disk_position() {
+                    return false;
+                }
+                ancestor = dir.find("..");
+            }
+        }
+        // easy-fs moves under the same name, the new one is given after
+        if new_parent.inode.find(old_name).is_some() || new_parent.inode.find(new_name).is_some() {
+            return false;
+        }
+        self.inode._move(old_name, new_parent.inode.clone()) && new_parent.inode._rename(old_name, new_name)
+    }
+}
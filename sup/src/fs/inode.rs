@@ -1,18 +1,15 @@
-//! `Arc<Inode>` -> `OSInodeInner`: In order to open files concurrently
-//! we need to wrap `Inode` into `Arc`,but `Mutex` in `Inode` prevents
-//! file systems from being accessed simultaneously
+//! `Arc<dyn VfsInode>` -> `OSInodeInner`: an open file of any mounted file
+//! system, the inode is shared by every open of it
 //!
-//! `UPSafeCell<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
-//! need to wrap `OSInodeInner` into `UPSafeCell`
-use super::File;
+//! `UPSafeCell<OSInodeInner>` -> `OSInode`: the offset changes through `&self`
+use super::vfs::{InodeType, VfsInode, resolve, resolve_parent};
+use super::{ENOSPC, File, open_device, open_fifo};
 use crate::mm::UserBuffer;
 use crate::sync::UPSafeCell;
-use crate::{drivers::BLOCK_DEVICE, fs::inode};
+use alloc::format;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
-use fs::{FileSystem, Inode};
-use lazy_static::*;
 /// A wrapper around a filesystem inode
 /// to implement File trait atop
 pub struct OSInode {
@@ -23,12 +20,12 @@ pub struct OSInode {
 /// The OS inode inner in 'UPSafeCell'
 pub struct OSInodeInner {
     offset: usize,
-    inode: Arc<Inode>,
+    inode: Arc<dyn VfsInode>,
 }
 
 impl OSInode {
     /// Construct an OS inode from a inode
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+    pub fn new(readable: bool, writable: bool, inode: Arc<dyn VfsInode>) -> Self {
         Self {
             readable,
             writable,
@@ -37,39 +34,35 @@ impl OSInode {
     }
     /// Read all data inside a inode into vector
     pub fn read_all(&self) -> Vec<u8> {
-        let mut inner = self.inner.exclusive_access();
+        let (inode, mut offset) = self.inode_and_offset();
         let mut buffer = [0u8; 512];
         let mut v: Vec<u8> = Vec::new();
         loop {
-            // println!("before read_at: offset = {}", inner.offset);
-            let len = inner.inode.read_at(inner.offset, &mut buffer);
-            // println!("after read_at: offset = {}, len = {}", inner.offset, len);
+            let len = inode.read_at(offset, &mut buffer);
             if len == 0 {
                 break;
             }
-            inner.offset += len;
+            offset += len;
             v.extend_from_slice(&buffer[..len]);
         }
+        self.inner.exclusive_access().offset = offset;
         v
     }
+    /// The inode and the offset, I/O may sleep so the inner part is not held
+    /// across it
+    fn inode_and_offset(&self) -> (Arc<dyn VfsInode>, usize) {
+        let inner = self.inner.exclusive_access();
+        (inner.inode.clone(), inner.offset)
+    }
 }
 
-lazy_static! {
-    pub static ref ROOT_INODE: Arc<Inode> = {
-        let fs = FileSystem::open(BLOCK_DEVICE.clone());
-        Arc::new(FileSystem::root_inode(&fs))
-    };
-    // pub static ref BIN_INODE: Arc<Inode> = {
-    //     ROOT_INODE.mkdir("bin").unwrap()
-    // };
-}
 /// List all files in the filesystems
 pub fn list_apps() {
     println!("/**** APPS ****");
-    let bin_inode = ROOT_INODE
-        .find("bin")
-        .unwrap_or_else(|| ROOT_INODE.create("bin").expect("No /bin directory"));
-    for app in bin_inode.ls() {
+    let bin_inode = resolve("bin")
+        .or_else(|| resolve("")?.create("bin", InodeType::Directory))
+        .expect("No /bin directory");
+    for app in bin_inode.readdir() {
         println!("/bin/{}", app);
     }
     println!("**************/");
@@ -88,6 +81,7 @@ bitflags! {
         const CREATE = 1 << 9;
         ///Clear file and return an empty one
         const TRUNC = 1 << 10;
+        const NONBLOCK = 1 << 11;
     }
 }
 
@@ -104,58 +98,98 @@ impl OpenFlags {
         }
     }
 }
-///Open file with flags
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+///Open file with flags, -1 or EINTR if it cannot be
+pub fn open_file(name: &str, flags: OpenFlags) -> Result<Arc<dyn File + Send + Sync>, isize> {
     let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            // clear size
-            // println!("\nopen: {}", name);
-            inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
-        } else {
-            // create file
-            // println!("\nopen create: {}", name);
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
+    let nonblock = flags.contains(OpenFlags::NONBLOCK);
+    let name = name.trim_start_matches('/');
+    if let Some(device) = name.strip_prefix("dev/") {
+        return open_device(device, flags).ok_or(-1);
+    }
+    let inode = match resolve(name) {
+        Some(inode) => {
+            match inode.stat().type_ {
+                // FIFOs hold no data, CREATE and TRUNC do not apply
+                InodeType::Fifo => {
+                    return open_fifo(inode.as_ref(), readable, writable, nonblock)
+                        .map(|pipe| pipe as Arc<dyn File + Send + Sync>);
+                }
+                // sockets are reached through connect
+                InodeType::Socket => return Err(-1),
+                InodeType::File if flags.intersects(OpenFlags::CREATE | OpenFlags::TRUNC) => {
+                    inode.truncate();
+                }
+                _ => {}
+            }
+            inode
         }
-    } else {
-        // println!("\nopen nocreate file: {}", name);
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
-                inode.clear();
+        None if flags.contains(OpenFlags::CREATE) => {
+            let created = resolve_parent(name)
+                .and_then(|(parent, name)| parent.create(&name, InodeType::File));
+            match created {
+                Some(inode) => inode,
+                None => return Err(-1),
             }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
-    }
+        }
+        None => return Err(-1),
+    };
+    Ok(Arc::new(OSInode::new(readable, writable, inode)))
+}
+
+/// The regular file at `path`
+fn open_regular(path: &str) -> Option<Arc<OSInode>> {
+    resolve(path)
+        .filter(|inode| inode.stat().type_ == InodeType::File)
+        .map(|inode| Arc::new(OSInode::new(true, false, inode)))
 }
 
 ///Open file with flags
 pub fn open_bin(name: &str) -> Option<Arc<OSInode>> {
-    let (readable, writable) = OpenFlags::RDONLY.read_write();
-    // println!("\nopen nocreate file: {}", name);
-    ROOT_INODE
-        .find_elf(name)
-        .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
+    if name.is_empty() {
+        return None;
+    }
+    open_regular(&format!("bin/{}", name))
+}
+
+/// Open a file to exec: bare names are searched in `bin`, paths from the root
+pub fn open_exec(path: &str) -> Option<Arc<OSInode>> {
+    if path.contains('/') {
+        open_regular(path)
+    } else {
+        open_bin(path)
+    }
 }
 
 pub fn mkdir_at_root(name: &str) -> Option<Arc<OSInode>> {
-    ROOT_INODE
-        .mkdir(name)
+    let (parent, name) = resolve_parent(name)?;
+    parent
+        .create(&name, InodeType::Directory)
         .map(|inode| Arc::new(OSInode::new(true, false, inode)))
 }
 
+pub fn mkfifo_at_root(name: &str) -> bool {
+    resolve_parent(name).is_some_and(|(parent, name)| parent.create(&name, InodeType::Fifo).is_some())
+}
+
 pub fn remove_at_root(name: &str) -> bool {
-    ROOT_INODE.remove(name)
+    resolve_parent(name).is_some_and(|(parent, name)| parent.unlink(&name))
 }
 
+/// Give the last component of `path` the name `new_name`, in the same directory
 pub fn rename_at_root(path: &str, new_name: &str) -> bool {
-    ROOT_INODE.rename(path, new_name)
+    if new_name.is_empty() || new_name.contains('/') {
+        return false;
+    }
+    resolve_parent(path).is_some_and(|(parent, name)| parent.rename(&name, &parent, new_name))
 }
 
+/// Move `old_path` into the directory `new_path` under the same name, both
+/// must be on the same file system
 pub fn move_at_root(old_path: &str, new_path: &str) -> bool {
-    ROOT_INODE.mv(old_path, new_path)
+    let Some((parent, name)) = resolve_parent(old_path) else {
+        return false;
+    };
+    resolve(new_path).is_some_and(|dst| parent.rename(&name, &dst, &name))
 }
 
 impl File for OSInode {
@@ -165,28 +199,33 @@ impl File for OSInode {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, mut buf: UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
-        let mut total_read_size = 0usize;
+    fn read(&self, mut buf: UserBuffer) -> isize {
+        let (inode, start) = self.inode_and_offset();
+        let mut offset = start;
         for slice in buf.buffers.iter_mut() {
-            let read_size = inner.inode.read_at(inner.offset, *slice);
-            if read_size == 0 {
+            let read_size = inode.read_at(offset, slice);
+            offset += read_size;
+            if read_size < slice.len() {
                 break;
             }
-            inner.offset += read_size;
-            total_read_size += read_size;
         }
-        total_read_size
+        self.inner.exclusive_access().offset = offset;
+        (offset - start) as isize
     }
-    fn write(&self, buf: UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
-        let mut total_write_size = 0usize;
+    fn write(&self, buf: UserBuffer) -> isize {
+        let (inode, start) = self.inode_and_offset();
+        let mut offset = start;
         for slice in buf.buffers.iter() {
-            let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
-            total_write_size += write_size;
+            let write_size = inode.write_at(offset, slice);
+            offset += write_size;
+            if write_size < slice.len() {
+                break;
+            }
+        }
+        self.inner.exclusive_access().offset = offset;
+        if offset == start && buf.len() > 0 {
+            return ENOSPC;
         }
-        total_write_size
+        (offset - start) as isize
     }
 }
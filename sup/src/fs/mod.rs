@@ -1,21 +1,104 @@
 //! File system in os
+mod devfs;
+mod easyfs;
 mod inode;
+mod mqueue;
 mod stdio;
+mod tmpfs;
 mod pipe;
+mod pty;
+mod random;
+mod socket;
+mod tty;
+mod vfs;
 
 use crate::mm::UserBuffer;
-/// File trait
-pub trait File: Send + Sync {
+use bitflags::*;
+use core::any::Any;
+/// File trait, `Any` lets syscalls reach the concrete type behind an fd
+pub trait File: Any + Send + Sync {
     /// If readable
     fn readable(&self) -> bool;
     /// If writable
     fn writable(&self) -> bool;
-    /// Read file to `UserBuffer`
-    fn read(&self, buf: UserBuffer) -> usize;
-    /// Write `UserBuffer` to file
-    fn write(&self, buf: UserBuffer) -> usize;
+    /// Read file to `UserBuffer`, return bytes read or a negative errno
+    fn read(&self, buf: UserBuffer) -> isize;
+    /// Write `UserBuffer` to file, return bytes written or a negative errno
+    fn write(&self, buf: UserBuffer) -> isize;
+    /// Events that are ready now, a file that never blocks is always ready
+    fn poll(&self) -> PollEvents {
+        let mut events = PollEvents::empty();
+        if self.readable() {
+            events |= PollEvents::IN;
+        }
+        if self.writable() {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+    /// Register the current task to be woken when `poll` may change, the
+    /// caller blocks right after. Nothing to do for a file that never blocks.
+    fn add_poll_waiter(&self) {}
+    /// Device specific request, only terminals and pipes know any
+    fn ioctl(&self, _request: usize, _arg: usize) -> isize {
+        ENOTTY
+    }
 }
 
-pub use inode::{OSInode, OpenFlags, list_apps, open_file, open_bin, mkdir_at_root, remove_at_root, rename_at_root, move_at_root};
-pub use pipe::{make_pipe, Pipe, PipeRingBuffer};
-pub use stdio::{Stdin, Stdout};
+bitflags! {
+    /// Events of `sys_ppoll`, same bits as POLLIN and friends
+    #[derive(Clone, Copy)]
+    pub struct PollEvents: u16 {
+        /// data to read, or end of file
+        const IN = 1 << 0;
+        /// room to write
+        const OUT = 1 << 2;
+        /// the other end of a pipe is gone for a writer
+        const ERR = 1 << 3;
+        /// the other end is gone for a reader
+        const HUP = 1 << 4;
+        /// fd is not open, only in `revents`
+        const NVAL = 1 << 5;
+    }
+}
+
+/// One entry of the array given to `sys_ppoll`, like `struct pollfd`
+#[repr(C)]
+pub struct PollFd {
+    /// ignored when negative
+    pub fd: i32,
+    pub events: u16,
+    pub revents: u16,
+}
+
+/// no such file or directory
+pub const ENOENT: isize = -2;
+/// a blocking call was interrupted by a signal
+pub const EINTR: isize = -4;
+/// the other end of a terminal is gone
+pub const EIO: isize = -5;
+/// no data or room right now on a non-blocking file
+pub const EAGAIN: isize = -11;
+/// a mount point is in use
+pub const EBUSY: isize = -16;
+/// no file system of the type given to mount
+pub const ENODEV: isize = -19;
+pub const ENOTDIR: isize = -20;
+pub const EINVAL: isize = -22;
+/// ioctl on a file that is not a terminal
+pub const ENOTTY: isize = -25;
+/// no room left on the device
+pub const ENOSPC: isize = -28;
+/// write with every read end closed
+pub const EPIPE: isize = -32;
+
+pub use devfs::{Console, DiskFile, Full, Null, Zero, open_device};
+pub use inode::{OSInode, OpenFlags, list_apps, open_file, open_bin, open_exec, mkdir_at_root, mkfifo_at_root, remove_at_root, rename_at_root, move_at_root};
+pub use mqueue::{open_mqueue, MqAttr, MqFile};
+pub use pipe::{make_pipe, open_fifo, Pipe, PipeRingBuffer};
+pub use pty::{open_ptmx, open_pts, PtyMaster, PtySlave, TIOCGPTN};
+pub use random::RandomDevice;
+pub use socket::{AF_UNIX, SOCK_DGRAM, SOCK_STREAM, Socket, SocketType};
+pub use stdio::{CONSOLE_TTY, Stdin, Stdout};
+pub use tty::{LineDiscipline, Termios, tty_ioctl, tty_read, tty_receive};
+pub use vfs::{FileSystemType, InodeType, Stat, VfsInode, init, list_mounts, mount, resolve, umount};
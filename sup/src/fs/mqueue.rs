@@ -0,0 +1,210 @@
+//! Named message queues
+use super::{EINTR, File, PollEvents};
+use crate::config::{MQ_DEFAULT_MAX_MSGS, MQ_DEFAULT_MSG_SIZE, MQ_MAX_MSGS, MQ_MAX_MSG_SIZE};
+use crate::mm::UserBuffer;
+use crate::sync::{UPSafeCell, WaitQueue};
+use crate::task::{block_current_and_run_next, current_signal_pending};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Queue of bounded-size messages, the highest priority is received first
+pub struct MessageQueue {
+    max_msgs: usize,
+    msg_size: usize,
+    /// priority -> messages in arrival order
+    messages: BTreeMap<u32, VecDeque<Vec<u8>>>,
+    count: usize,
+    read_wait: WaitQueue,
+    write_wait: WaitQueue,
+}
+
+impl MessageQueue {
+    pub fn new(max_msgs: usize, msg_size: usize) -> Self {
+        Self {
+            max_msgs,
+            msg_size,
+            messages: BTreeMap::new(),
+            count: 0,
+            read_wait: WaitQueue::new(),
+            write_wait: WaitQueue::new(),
+        }
+    }
+    fn push(&mut self, msg: Vec<u8>, prio: u32) {
+        self.messages.entry(prio).or_default().push_back(msg);
+        self.count += 1;
+    }
+    fn pop(&mut self) -> Option<(Vec<u8>, u32)> {
+        let mut entry = self.messages.last_entry()?;
+        let prio = *entry.key();
+        let msg = entry.get_mut().pop_front().unwrap();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        self.count -= 1;
+        Some((msg, prio))
+    }
+}
+
+/// Attributes given to `sys_mq_open` when creating a queue
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct MqAttr {
+    pub max_msgs: usize,
+    pub msg_size: usize,
+}
+
+impl Default for MqAttr {
+    fn default() -> Self {
+        Self {
+            max_msgs: MQ_DEFAULT_MAX_MSGS,
+            msg_size: MQ_DEFAULT_MSG_SIZE,
+        }
+    }
+}
+
+/// An opened message queue, one end of it lives in the fd table
+pub struct MqFile {
+    readable: bool,
+    writable: bool,
+    queue: Arc<UPSafeCell<MessageQueue>>,
+}
+
+lazy_static! {
+    /// queues stay addressable by name while some fd refers to them
+    static ref MQ_TABLE: UPSafeCell<BTreeMap<String, Weak<UPSafeCell<MessageQueue>>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Open the queue `name`, creating it with `attr` if `create` is set
+pub fn open_mqueue(
+    name: &str,
+    readable: bool,
+    writable: bool,
+    create: bool,
+    attr: MqAttr,
+) -> Option<Arc<MqFile>> {
+    let mut table = MQ_TABLE.exclusive_access();
+    table.retain(|_, queue| queue.strong_count() > 0);
+    let queue = match table.get(name).and_then(|queue| queue.upgrade()) {
+        Some(queue) => queue,
+        None if create => {
+            if attr.max_msgs == 0
+                || attr.max_msgs > MQ_MAX_MSGS
+                || attr.msg_size == 0
+                || attr.msg_size > MQ_MAX_MSG_SIZE
+            {
+                return None;
+            }
+            let queue = Arc::new(unsafe {
+                UPSafeCell::new(MessageQueue::new(attr.max_msgs, attr.msg_size))
+            });
+            table.insert(String::from(name), Arc::downgrade(&queue));
+            queue
+        }
+        None => return None,
+    };
+    Some(Arc::new(MqFile {
+        readable,
+        writable,
+        queue,
+    }))
+}
+
+impl MqFile {
+    /// Send one message, blocking while the queue is full.
+    /// Return -1 if the message is larger than the queue allows, EINTR if a
+    /// signal came while waiting.
+    pub fn send(&self, buf: UserBuffer, prio: u32) -> isize {
+        if buf.len() > self.queue.exclusive_access().msg_size {
+            return -1;
+        }
+        let mut msg = Vec::with_capacity(buf.len());
+        for part in buf.buffers.iter() {
+            msg.extend_from_slice(part);
+        }
+        loop {
+            let mut queue = self.queue.exclusive_access();
+            if queue.count < queue.max_msgs {
+                queue.push(msg, prio);
+                queue.read_wait.wake_all();
+                return 0;
+            }
+            queue.write_wait.add_current();
+            drop(queue);
+            block_current_and_run_next();
+            if current_signal_pending() {
+                return EINTR;
+            }
+        }
+    }
+    /// Receive the oldest message of the highest priority, blocking while the
+    /// queue is empty. Return its length and priority, -1 if `buf` is
+    /// smaller than the message size of the queue or EINTR if a signal came.
+    pub fn receive(&self, mut buf: UserBuffer) -> Result<(usize, u32), isize> {
+        if buf.len() < self.queue.exclusive_access().msg_size {
+            return Err(-1);
+        }
+        loop {
+            let mut queue = self.queue.exclusive_access();
+            if let Some((msg, prio)) = queue.pop() {
+                queue.write_wait.wake_all();
+                drop(queue);
+                let mut copied = 0;
+                for part in buf.buffers.iter_mut() {
+                    let len = part.len().min(msg.len() - copied);
+                    part[..len].copy_from_slice(&msg[copied..copied + len]);
+                    copied += len;
+                }
+                return Ok((msg.len(), prio));
+            }
+            queue.read_wait.add_current();
+            drop(queue);
+            block_current_and_run_next();
+            if current_signal_pending() {
+                return Err(EINTR);
+            }
+        }
+    }
+}
+
+impl File for MqFile {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, buf: UserBuffer) -> isize {
+        self.receive(buf).map_or_else(|errno| errno, |(len, _)| len as isize)
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        let len = buf.len();
+        match self.send(buf, 0) {
+            0 => len as isize,
+            errno => errno,
+        }
+    }
+    fn poll(&self) -> PollEvents {
+        let queue = self.queue.exclusive_access();
+        let mut events = PollEvents::empty();
+        if self.readable && queue.count > 0 {
+            events |= PollEvents::IN;
+        }
+        if self.writable && queue.count < queue.max_msgs {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+    fn add_poll_waiter(&self) {
+        let mut queue = self.queue.exclusive_access();
+        if self.readable {
+            queue.read_wait.add_current();
+        }
+        if self.writable {
+            queue.write_wait.add_current();
+        }
+    }
+}
@@ -1,112 +1,215 @@
-use super::File;
+use super::vfs::VfsInode;
+use super::{EAGAIN, EBUSY, EINTR, EINVAL, ENOTTY, EPIPE, File, PollEvents};
+use crate::config::{PAGE_SIZE, PIPE_BUFFER_SIZE, PIPE_MAX_SIZE};
 use crate::mm::UserBuffer;
-use crate::sync::UPSafeCell;
+use crate::sync::{UPSafeCell, WaitQueue};
+use alloc::collections::BTreeMap;
 use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::*;
 
-use crate::task::suspend_current_and_run_next;
+use crate::task::{block_current_and_run_next, current_signal_pending};
+
+/// fcntl requests of Linux, taken by ioctl here as there is no fcntl
+pub const F_SETPIPE_SZ: usize = 1031;
+pub const F_GETPIPE_SZ: usize = 1032;
 
 pub struct Pipe {
     readable: bool,
     writable: bool,
+    /// return EAGAIN instead of blocking
+    nonblock: bool,
     buffer: Arc<UPSafeCell<PipeRingBuffer>>,
 }
 
-const RING_BUFFER_SIZE: usize = 32;
-
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    FULL,
-    EMPTY,
-    NORMAL,
-}
-
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    arr: Vec<u8>,
     head: usize,
-    tail: usize,
-    status: RingBufferStatus,
-    write_end: Option<Weak<Pipe>>,
+    /// bytes stored from `head`
+    len: usize,
+    read_ends: Vec<Weak<Pipe>>,
+    write_ends: Vec<Weak<Pipe>>,
+    /// readers waiting for data or for the last writer to leave
+    read_wait: WaitQueue,
+    /// writers waiting for room or for the last reader to leave
+    write_wait: WaitQueue,
+    /// FIFO openers waiting for the other end
+    open_wait: WaitQueue,
 }
 
 impl Pipe {
-    pub fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>, nonblock: bool) -> Self {
         Self {
             readable: true,
             writable: false,
+            nonblock,
             buffer,
         }
     }
-    pub fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>, nonblock: bool) -> Self {
         Self {
             readable: false,
             writable: true,
+            nonblock,
             buffer,
         }
     }
 }
 
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // peers blocked on us must notice EOF or EPIPE
+        let mut ring_buffer = self.buffer.exclusive_access();
+        ring_buffer.read_wait.wake_all();
+        ring_buffer.write_wait.wake_all();
+    }
+}
+
 impl PipeRingBuffer {
     pub fn new() -> Self {
+        Self::with_capacity(PIPE_BUFFER_SIZE)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
+            arr: vec![0; capacity],
             head: 0,
-            tail: 0,
-            status: RingBufferStatus::EMPTY,
-            write_end: None,
+            len: 0,
+            read_ends: Vec::new(),
+            write_ends: Vec::new(),
+            read_wait: WaitQueue::new(),
+            write_wait: WaitQueue::new(),
+            open_wait: WaitQueue::new(),
         }
     }
-    pub fn set_write_end(&mut self, write_end: &Arc<Pipe>) {
-        self.write_end = Some(Arc::downgrade(write_end));
+    pub fn add_read_end(&mut self, read_end: &Arc<Pipe>) {
+        self.read_ends.retain(|read_end| read_end.strong_count() > 0);
+        self.read_ends.push(Arc::downgrade(read_end));
+        self.open_wait.wake_all();
     }
-    pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::NORMAL;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::EMPTY;
-        }
-        c
-    }
-    pub fn write_byte(&mut self, c: u8) {
-        self.status = RingBufferStatus::NORMAL;
-        self.arr[self.tail] = c;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::FULL;
+    pub fn add_write_end(&mut self, write_end: &Arc<Pipe>) {
+        self.write_ends.retain(|write_end| write_end.strong_count() > 0);
+        self.write_ends.push(Arc::downgrade(write_end));
+        self.open_wait.wake_all();
+    }
+    /// Copy out as much as fits in `buf`, return the bytes copied
+    pub fn read_into(&mut self, buf: &mut [u8]) -> usize {
+        let capacity = self.arr.len();
+        let n = buf.len().min(self.len);
+        let first = n.min(capacity - self.head);
+        buf[..first].copy_from_slice(&self.arr[self.head..self.head + first]);
+        buf[first..n].copy_from_slice(&self.arr[..n - first]);
+        self.head = (self.head + n) % capacity;
+        self.len -= n;
+        n
+    }
+    /// Copy in as much of `buf` as there is room for, return the bytes copied
+    pub fn write_from(&mut self, buf: &[u8]) -> usize {
+        let capacity = self.arr.len();
+        let n = buf.len().min(capacity - self.len);
+        let tail = (self.head + self.len) % capacity;
+        let first = n.min(capacity - tail);
+        self.arr[tail..tail + first].copy_from_slice(&buf[..first]);
+        self.arr[..n - first].copy_from_slice(&buf[first..n]);
+        self.len += n;
+        n
+    }
+    /// Move the data into a buffer of `capacity` bytes, false if it does not fit
+    pub fn resize(&mut self, capacity: usize) -> bool {
+        if capacity < self.len {
+            return false;
         }
+        let mut arr = vec![0; capacity];
+        let len = self.len;
+        self.read_into(&mut arr[..len]);
+        self.arr = arr;
+        self.head = 0;
+        self.len = len;
+        true
     }
     pub fn available_read(&self) -> usize {
-        if self.status == RingBufferStatus::EMPTY {
-            0
-        } else {
-            if self.tail > self.head {
-                self.tail - self.head
-            } else {
-                self.tail + RING_BUFFER_SIZE - self.head
-            }
-        }
+        self.len
     }
     pub fn available_write(&self) -> usize {
-        if self.status == RingBufferStatus::FULL {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
-        }
+        self.arr.len() - self.len
+    }
+    pub fn all_read_ends_closed(&self) -> bool {
+        self.read_ends.iter().all(|read_end| read_end.strong_count() == 0)
     }
     pub fn all_write_ends_closed(&self) -> bool {
-        self.write_end.as_ref().unwrap().upgrade().is_none()
+        self.write_ends.iter().all(|write_end| write_end.strong_count() == 0)
     }
 }
 
 /// Return (read_end, write_end)
-pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+pub fn make_pipe(nonblock: bool) -> (Arc<Pipe>, Arc<Pipe>) {
     let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
-    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
-    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
-    buffer.exclusive_access().set_write_end(&write_end);
+    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone(), nonblock));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone(), nonblock));
+    buffer.exclusive_access().add_read_end(&read_end);
+    buffer.exclusive_access().add_write_end(&write_end);
     (read_end, write_end)
 }
 
+lazy_static! {
+    /// buffers of opened FIFOs, keyed by the device and number of their inode
+    static ref FIFO_BUFFERS: UPSafeCell<BTreeMap<(usize, usize), Weak<UPSafeCell<PipeRingBuffer>>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Open an end of the FIFO `inode`. Openers of the same inode share one buffer,
+/// and opening blocks until both a reader and a writer exist.
+/// A non-blocking reader does not wait, a non-blocking writer fails without a reader.
+/// A signal while waiting gives EINTR.
+pub fn open_fifo(inode: &dyn VfsInode, readable: bool, writable: bool, nonblock: bool) -> Result<Arc<Pipe>, isize> {
+    let buffer = {
+        let mut fifos = FIFO_BUFFERS.exclusive_access();
+        fifos.retain(|_, buffer| buffer.strong_count() > 0);
+        let stat = inode.stat();
+        let key = (stat.dev, stat.ino);
+        match fifos.get(&key).and_then(Weak::upgrade) {
+            Some(buffer) => buffer,
+            None => {
+                let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+                fifos.insert(key, Arc::downgrade(&buffer));
+                buffer
+            }
+        }
+    };
+    if nonblock && writable && !readable && buffer.exclusive_access().all_read_ends_closed() {
+        return Err(-1);
+    }
+    let pipe = Arc::new(Pipe {
+        readable,
+        writable,
+        nonblock,
+        buffer: buffer.clone(),
+    });
+    let mut ring_buffer = buffer.exclusive_access();
+    if readable {
+        ring_buffer.add_read_end(&pipe);
+    }
+    if writable {
+        ring_buffer.add_write_end(&pipe);
+    }
+    drop(ring_buffer);
+    loop {
+        let mut ring_buffer = buffer.exclusive_access();
+        if nonblock
+            || ((!readable || !ring_buffer.all_write_ends_closed())
+                && (!writable || !ring_buffer.all_read_ends_closed()))
+        {
+            return Ok(pipe);
+        }
+        ring_buffer.open_wait.add_current();
+        drop(ring_buffer);
+        block_current_and_run_next();
+        if current_signal_pending() {
+            return Err(EINTR);
+        }
+    }
+}
+
 impl File for Pipe {
     fn readable(&self) -> bool {
         self.readable
@@ -114,62 +217,125 @@ impl File for Pipe {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, buf: UserBuffer) -> usize {
+    /// Block only while the pipe is empty, then return whatever is there
+    fn read(&self, mut buf: UserBuffer) -> isize {
         assert!(self.readable());
-        let want_to_read = buf.len();
-        let mut buf_iter = buf.into_iter();
-        let mut already_read = 0usize;
         loop {
             let mut ring_buffer = self.buffer.exclusive_access();
-            let loop_read = ring_buffer.available_read();
-            if loop_read == 0 {
+            if ring_buffer.available_read() == 0 {
                 if ring_buffer.all_write_ends_closed() {
-                    return already_read;
+                    return 0;
+                }
+                if self.nonblock {
+                    return EAGAIN;
+                }
+                if current_signal_pending() {
+                    return EINTR;
                 }
+                ring_buffer.read_wait.add_current();
                 drop(ring_buffer);
-                suspend_current_and_run_next();
+                block_current_and_run_next();
                 continue;
             }
-            for _ in 0..loop_read {
-                if let Some(byte_ref) = buf_iter.next() {
-                    unsafe {
-                        *byte_ref = ring_buffer.read_byte();
-                    }
-                    already_read += 1;
-                    if already_read == want_to_read {
-                        return want_to_read;
-                    }
-                } else {
-                    return already_read;
+            let mut already_read = 0usize;
+            for slice in buf.buffers.iter_mut() {
+                let n = ring_buffer.read_into(slice);
+                already_read += n;
+                if n < slice.len() {
+                    break;
                 }
             }
+            ring_buffer.write_wait.wake_all();
+            return already_read as isize;
         }
     }
-    fn write(&self, buf: UserBuffer) -> usize {
+    /// Block until everything is written, unless non-blocking
+    fn write(&self, buf: UserBuffer) -> isize {
         assert!(self.writable());
-        let want_to_write = buf.len();
-        let mut buf_iter = buf.into_iter();
         let mut already_write = 0usize;
+        let (mut slice_idx, mut slice_offset) = (0usize, 0usize);
         loop {
             let mut ring_buffer = self.buffer.exclusive_access();
-            let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
+            if ring_buffer.all_read_ends_closed() {
+                return if already_write > 0 { already_write as isize } else { EPIPE };
+            }
+            if ring_buffer.available_write() == 0 {
+                if self.nonblock {
+                    return if already_write > 0 { already_write as isize } else { EAGAIN };
+                }
+                if current_signal_pending() {
+                    return if already_write > 0 { already_write as isize } else { EINTR };
+                }
+                ring_buffer.write_wait.add_current();
                 drop(ring_buffer);
-                suspend_current_and_run_next();
+                block_current_and_run_next();
                 continue;
             }
-            // write at most loop_write bytes
-            for _ in 0..loop_write {
-                if let Some(byte_ref) = buf_iter.next() {
-                    ring_buffer.write_byte(unsafe { *byte_ref });
-                    already_write += 1;
-                    if already_write == want_to_write {
-                        return want_to_write;
-                    }
-                } else {
-                    return already_write;
+            while slice_idx < buf.buffers.len() {
+                let slice = &buf.buffers[slice_idx][slice_offset..];
+                let n = ring_buffer.write_from(slice);
+                already_write += n;
+                if n < slice.len() {
+                    slice_offset += n;
+                    break;
+                }
+                slice_idx += 1;
+                slice_offset = 0;
+            }
+            ring_buffer.read_wait.wake_all();
+            if slice_idx == buf.buffers.len() {
+                return already_write as isize;
+            }
+        }
+    }
+    fn poll(&self) -> PollEvents {
+        let ring_buffer = self.buffer.exclusive_access();
+        let mut events = PollEvents::empty();
+        if self.readable {
+            if ring_buffer.available_read() > 0 {
+                events |= PollEvents::IN;
+            }
+            if ring_buffer.all_write_ends_closed() {
+                events |= PollEvents::HUP;
+            }
+        }
+        if self.writable {
+            if ring_buffer.all_read_ends_closed() {
+                events |= PollEvents::ERR;
+            } else if ring_buffer.available_write() > 0 {
+                events |= PollEvents::OUT;
+            }
+        }
+        events
+    }
+    fn add_poll_waiter(&self) {
+        let mut ring_buffer = self.buffer.exclusive_access();
+        if self.readable {
+            ring_buffer.read_wait.add_current();
+        }
+        if self.writable {
+            ring_buffer.write_wait.add_current();
+        }
+    }
+    /// F_GETPIPE_SZ, and F_SETPIPE_SZ with the size in `arg` rounded up to
+    /// whole pages. Both give the capacity.
+    fn ioctl(&self, request: usize, arg: usize) -> isize {
+        let mut ring_buffer = self.buffer.exclusive_access();
+        match request {
+            F_GETPIPE_SZ => ring_buffer.arr.len() as isize,
+            F_SETPIPE_SZ => {
+                if arg > PIPE_MAX_SIZE {
+                    return EINVAL;
+                }
+                let capacity = arg.max(1).next_multiple_of(PAGE_SIZE);
+                if !ring_buffer.resize(capacity) {
+                    return EBUSY;
                 }
+                // there may be room now
+                ring_buffer.write_wait.wake_all();
+                capacity as isize
             }
+            _ => ENOTTY,
         }
     }
 }
@@ -0,0 +1,255 @@
+//! Pseudo-terminals: opening `dev/ptmx` makes a master, `dev/pts/<n>` is its slave.
+//! What the master writes is input to the slave line discipline, what the
+//! slave writes and echoes is read from the master.
+use super::{EAGAIN, EINTR, EIO, File, LineDiscipline, PollEvents, tty_ioctl, tty_read, tty_receive};
+use crate::config::TTY_BUFFER_SIZE;
+use crate::mm::{UserBuffer, translated_refmut};
+use crate::sync::{UPSafeCell, WaitQueue};
+use crate::task::{block_current_and_run_next, current_signal_pending, current_user_token};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// get the number of the slave of a master
+pub const TIOCGPTN: usize = 0x8004_5430;
+
+pub struct Pty {
+    index: usize,
+    /// line discipline seen by the slave
+    tty: UPSafeCell<LineDiscipline>,
+    output: UPSafeCell<PtyOutput>,
+}
+
+/// Bytes on their way from the slave to the master
+struct PtyOutput {
+    data: VecDeque<u8>,
+    slaves: usize,
+    master_closed: bool,
+    read_wait: WaitQueue,
+    write_wait: WaitQueue,
+}
+
+pub struct PtyMaster {
+    nonblock: bool,
+    pty: Arc<Pty>,
+}
+
+pub struct PtySlave {
+    nonblock: bool,
+    pty: Arc<Pty>,
+}
+
+lazy_static! {
+    /// pseudo-terminals whose master is open, by number
+    static ref PTY_TABLE: UPSafeCell<BTreeMap<usize, Weak<Pty>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Make a new pseudo-terminal and return its master
+pub fn open_ptmx(nonblock: bool) -> Arc<PtyMaster> {
+    let mut table = PTY_TABLE.exclusive_access();
+    table.retain(|_, pty| pty.strong_count() > 0);
+    let index = (0..).find(|index| !table.contains_key(index)).unwrap();
+    let pty = Arc::new(Pty {
+        index,
+        tty: unsafe { UPSafeCell::new(LineDiscipline::new()) },
+        output: unsafe {
+            UPSafeCell::new(PtyOutput {
+                data: VecDeque::new(),
+                slaves: 0,
+                master_closed: false,
+                read_wait: WaitQueue::new(),
+                write_wait: WaitQueue::new(),
+            })
+        },
+    });
+    table.insert(index, Arc::downgrade(&pty));
+    Arc::new(PtyMaster { nonblock, pty })
+}
+
+/// Open the slave of pseudo-terminal `index`, its master must be open
+pub fn open_pts(index: usize, nonblock: bool) -> Option<Arc<PtySlave>> {
+    let pty = PTY_TABLE.exclusive_access().get(&index)?.upgrade()?;
+    let mut output = pty.output.exclusive_access();
+    if output.master_closed {
+        return None;
+    }
+    output.slaves += 1;
+    drop(output);
+    Some(Arc::new(PtySlave { nonblock, pty }))
+}
+
+impl Pty {
+    /// Queue bytes for the master, blocking while the queue is full
+    fn push_output(&self, bytes: &[u8], nonblock: bool) -> isize {
+        let mut written = 0;
+        loop {
+            let mut output = self.output.exclusive_access();
+            if output.master_closed {
+                return EIO;
+            }
+            let n = (TTY_BUFFER_SIZE - output.data.len().min(TTY_BUFFER_SIZE)).min(bytes.len() - written);
+            output.data.extend(&bytes[written..written + n]);
+            written += n;
+            if n > 0 {
+                output.read_wait.wake_all();
+            }
+            if written == bytes.len() {
+                return written as isize;
+            }
+            if nonblock {
+                return if written > 0 { written as isize } else { EAGAIN };
+            }
+            if current_signal_pending() {
+                return if written > 0 { written as isize } else { EINTR };
+            }
+            output.write_wait.add_current();
+            drop(output);
+            block_current_and_run_next();
+        }
+    }
+}
+
+impl Drop for PtyMaster {
+    fn drop(&mut self) {
+        let mut output = self.pty.output.exclusive_access();
+        output.master_closed = true;
+        output.write_wait.wake_all();
+        drop(output);
+        self.pty.tty.exclusive_access().hang_up();
+    }
+}
+
+impl Drop for PtySlave {
+    fn drop(&mut self) {
+        let mut output = self.pty.output.exclusive_access();
+        output.slaves -= 1;
+        output.read_wait.wake_all();
+    }
+}
+
+impl File for PtyMaster {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    /// Read what the slave wrote, end of file once every slave is closed
+    fn read(&self, buf: UserBuffer) -> isize {
+        let mut output = loop {
+            let mut output = self.pty.output.exclusive_access();
+            if !output.data.is_empty() || output.slaves == 0 {
+                break output;
+            }
+            if self.nonblock {
+                return EAGAIN;
+            }
+            if current_signal_pending() {
+                return EINTR;
+            }
+            output.read_wait.add_current();
+            drop(output);
+            block_current_and_run_next();
+        };
+        let mut read = 0;
+        for byte in buf {
+            let Some(c) = output.data.pop_front() else {
+                break;
+            };
+            unsafe {
+                *byte = c;
+            }
+            read += 1;
+        }
+        output.write_wait.wake_all();
+        read
+    }
+    /// Type into the slave terminal
+    fn write(&self, buf: UserBuffer) -> isize {
+        let mut echo = Vec::new();
+        for part in buf.buffers.iter() {
+            for &c in part.iter() {
+                tty_receive(&self.pty.tty, c, &mut echo);
+            }
+        }
+        if !echo.is_empty() {
+            // echo that does not fit is dropped rather than blocking the typist
+            let mut output = self.pty.output.exclusive_access();
+            echo.truncate(TTY_BUFFER_SIZE - output.data.len().min(TTY_BUFFER_SIZE));
+            output.data.extend(echo);
+            output.read_wait.wake_all();
+        }
+        buf.len() as isize
+    }
+    fn poll(&self) -> PollEvents {
+        let output = self.pty.output.exclusive_access();
+        let mut events = PollEvents::OUT;
+        if !output.data.is_empty() {
+            events |= PollEvents::IN;
+        }
+        if output.slaves == 0 {
+            events |= PollEvents::HUP;
+        }
+        events
+    }
+    fn add_poll_waiter(&self) {
+        self.pty.output.exclusive_access().read_wait.add_current();
+    }
+    /// TIOCGPTN, anything else goes to the terminal of the slave
+    fn ioctl(&self, request: usize, arg: usize) -> isize {
+        if request == TIOCGPTN {
+            *translated_refmut(current_user_token(), arg as *mut u32) = self.pty.index as u32;
+            return 0;
+        }
+        tty_ioctl(&self.pty.tty, request, arg)
+    }
+}
+
+impl File for PtySlave {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> isize {
+        tty_read(&self.pty.tty, buf, self.nonblock)
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        let mut out = Vec::with_capacity(buf.len());
+        let tty = self.pty.tty.exclusive_access();
+        for part in buf.buffers.iter() {
+            tty.output(part, &mut out);
+        }
+        drop(tty);
+        match self.pty.push_output(&out, self.nonblock) {
+            n if n < 0 => n,
+            // report the bytes given, not the processed ones
+            n if n as usize == out.len() => buf.len() as isize,
+            n => n.min(buf.len() as isize),
+        }
+    }
+    fn poll(&self) -> PollEvents {
+        let tty = self.pty.tty.exclusive_access();
+        if tty.is_hung_up() {
+            return PollEvents::IN | PollEvents::HUP;
+        }
+        let mut events = PollEvents::empty();
+        if tty.has_input() {
+            events |= PollEvents::IN;
+        }
+        if self.pty.output.exclusive_access().data.len() < TTY_BUFFER_SIZE {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+    fn add_poll_waiter(&self) {
+        self.pty.tty.exclusive_access().read_wait.add_current();
+        self.pty.output.exclusive_access().write_wait.add_current();
+    }
+    fn ioctl(&self, request: usize, arg: usize) -> isize {
+        tty_ioctl(&self.pty.tty, request, arg)
+    }
+}
@@ -0,0 +1,429 @@
+//! Unix-domain sockets
+//!
+//! A connected stream socket is a pair of pipes, one for each direction.
+//! A datagram socket owns a queue of messages that its peers send into.
+use super::vfs::{InodeType, resolve, resolve_parent};
+use super::{EAGAIN, EINTR, EPIPE, File, Pipe, PipeRingBuffer, PollEvents};
+use crate::config::{PIPE_BUFFER_SIZE, UNIX_DGRAM_MAX_MSGS, UNIX_MAX_BACKLOG};
+use crate::mm::UserBuffer;
+use crate::sync::{UPSafeCell, WaitQueue};
+use crate::task::{block_current_and_run_next, current_signal_pending};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+pub const AF_UNIX: usize = 1;
+pub const SOCK_STREAM: usize = 1;
+pub const SOCK_DGRAM: usize = 2;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SocketType {
+    Stream,
+    Datagram,
+}
+
+pub struct Socket {
+    type_: SocketType,
+    /// return EAGAIN instead of blocking
+    nonblock: bool,
+    inner: UPSafeCell<SocketInner>,
+}
+
+struct SocketInner {
+    /// messages sent to a datagram socket
+    dgram_rx: Option<Arc<UPSafeCell<DgramQueue>>>,
+    state: SocketState,
+}
+
+enum SocketState {
+    Unconnected,
+    Listening(Arc<UPSafeCell<Listener>>),
+    /// read from `rx`, write to `tx`
+    Stream {
+        rx: Arc<Pipe>,
+        tx: Arc<Pipe>,
+    },
+    /// write to the queue of the peer
+    Datagram(Weak<UPSafeCell<DgramQueue>>),
+}
+
+/// Connections waiting for `accept`
+struct Listener {
+    backlog: usize,
+    pending: VecDeque<Arc<Socket>>,
+    /// tasks in `accept`
+    accept_wait: WaitQueue,
+    /// tasks in `connect` while the backlog is full
+    connect_wait: WaitQueue,
+}
+
+struct DgramQueue {
+    msgs: VecDeque<Vec<u8>>,
+    read_wait: WaitQueue,
+    write_wait: WaitQueue,
+}
+
+impl DgramQueue {
+    fn new() -> Arc<UPSafeCell<Self>> {
+        Arc::new(unsafe {
+            UPSafeCell::new(Self {
+                msgs: VecDeque::new(),
+                read_wait: WaitQueue::new(),
+                write_wait: WaitQueue::new(),
+            })
+        })
+    }
+}
+
+lazy_static! {
+    /// bound sockets, keyed by the device and number of their inode
+    static ref SOCKET_TABLE: UPSafeCell<BTreeMap<(usize, usize), Weak<Socket>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Return (read_end, write_end) of one direction of a stream
+fn stream_pipe(reader_nonblock: bool, writer_nonblock: bool) -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone(), reader_nonblock));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone(), writer_nonblock));
+    buffer.exclusive_access().add_read_end(&read_end);
+    buffer.exclusive_access().add_write_end(&write_end);
+    (read_end, write_end)
+}
+
+impl Socket {
+    fn with_state(type_: SocketType, nonblock: bool, state: SocketState) -> Arc<Self> {
+        let dgram_rx = (type_ == SocketType::Datagram).then(DgramQueue::new);
+        Arc::new(Self {
+            type_,
+            nonblock,
+            inner: unsafe { UPSafeCell::new(SocketInner { dgram_rx, state }) },
+        })
+    }
+    pub fn new(type_: SocketType, nonblock: bool) -> Arc<Self> {
+        Self::with_state(type_, nonblock, SocketState::Unconnected)
+    }
+    /// Two sockets connected to each other
+    pub fn pair(type_: SocketType, nonblock: bool) -> (Arc<Self>, Arc<Self>) {
+        match type_ {
+            SocketType::Stream => {
+                let (a_rx, b_tx) = stream_pipe(nonblock, nonblock);
+                let (b_rx, a_tx) = stream_pipe(nonblock, nonblock);
+                (
+                    Self::with_state(type_, nonblock, SocketState::Stream { rx: a_rx, tx: a_tx }),
+                    Self::with_state(type_, nonblock, SocketState::Stream { rx: b_rx, tx: b_tx }),
+                )
+            }
+            SocketType::Datagram => {
+                let (a, b) = (Self::new(type_, nonblock), Self::new(type_, nonblock));
+                a.inner.exclusive_access().state = SocketState::Datagram(b.dgram_queue());
+                b.inner.exclusive_access().state = SocketState::Datagram(a.dgram_queue());
+                (a, b)
+            }
+        }
+    }
+    fn dgram_queue(&self) -> Weak<UPSafeCell<DgramQueue>> {
+        Arc::downgrade(self.inner.exclusive_access().dgram_rx.as_ref().unwrap())
+    }
+    /// Create the socket file `path` and make the socket reachable through it.
+    /// Fail if `path` exists already.
+    pub fn bind(self: &Arc<Self>, path: &str) -> bool {
+        let Some((parent, name)) = resolve_parent(path) else {
+            return false;
+        };
+        let Some(inode) = parent.create(&name, InodeType::Socket) else {
+            return false;
+        };
+        let stat = inode.stat();
+        let mut table = SOCKET_TABLE.exclusive_access();
+        table.retain(|_, socket| socket.strong_count() > 0);
+        table.insert((stat.dev, stat.ino), Arc::downgrade(self));
+        true
+    }
+    /// Start accepting connections on a stream socket
+    pub fn listen(&self, backlog: usize) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        match &inner.state {
+            SocketState::Unconnected if self.type_ == SocketType::Stream => {
+                inner.state = SocketState::Listening(Arc::new(unsafe {
+                    UPSafeCell::new(Listener {
+                        backlog: backlog.clamp(1, UNIX_MAX_BACKLOG),
+                        pending: VecDeque::new(),
+                        accept_wait: WaitQueue::new(),
+                        connect_wait: WaitQueue::new(),
+                    })
+                }));
+                true
+            }
+            SocketState::Listening(listener) => {
+                listener.exclusive_access().backlog = backlog.clamp(1, UNIX_MAX_BACKLOG);
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Wait for a connection and return the server end of it
+    pub fn accept(&self) -> Result<Arc<Socket>, isize> {
+        let listener = match &self.inner.exclusive_access().state {
+            SocketState::Listening(listener) => listener.clone(),
+            _ => return Err(-1),
+        };
+        loop {
+            let mut inner = listener.exclusive_access();
+            if let Some(socket) = inner.pending.pop_front() {
+                inner.connect_wait.wake_all();
+                return Ok(socket);
+            }
+            if self.nonblock {
+                return Err(EAGAIN);
+            }
+            inner.accept_wait.add_current();
+            drop(inner);
+            block_current_and_run_next();
+            if current_signal_pending() {
+                return Err(EINTR);
+            }
+        }
+    }
+    /// Connect to the socket bound to `path`. A stream connection waits in
+    /// the backlog of the listener until accepted, a datagram socket only
+    /// remembers where to send.
+    pub fn connect(&self, path: &str) -> isize {
+        if !matches!(
+            self.inner.exclusive_access().state,
+            SocketState::Unconnected
+        ) {
+            return -1;
+        }
+        let Some(stat) = resolve(path)
+            .map(|inode| inode.stat())
+            .filter(|stat| stat.type_ == InodeType::Socket)
+        else {
+            return -1;
+        };
+        let key = (stat.dev, stat.ino);
+        loop {
+            let Some(peer) = SOCKET_TABLE
+                .exclusive_access()
+                .get(&key)
+                .and_then(Weak::upgrade)
+                .filter(|peer| peer.type_ == self.type_)
+            else {
+                // nobody is bound there anymore
+                return -1;
+            };
+            if self.type_ == SocketType::Datagram {
+                self.inner.exclusive_access().state = SocketState::Datagram(peer.dgram_queue());
+                return 0;
+            }
+            let listener = match &peer.inner.exclusive_access().state {
+                SocketState::Listening(listener) => listener.clone(),
+                _ => return -1,
+            };
+            drop(peer);
+            let mut inner = listener.exclusive_access();
+            if inner.pending.len() >= inner.backlog {
+                if self.nonblock {
+                    return EAGAIN;
+                }
+                inner.connect_wait.add_current();
+                drop(inner);
+                drop(listener);
+                block_current_and_run_next();
+                if current_signal_pending() {
+                    return EINTR;
+                }
+                continue;
+            }
+            let (rx, server_tx) = stream_pipe(self.nonblock, false);
+            let (server_rx, tx) = stream_pipe(false, self.nonblock);
+            inner.pending.push_back(Self::with_state(
+                SocketType::Stream,
+                false,
+                SocketState::Stream {
+                    rx: server_rx,
+                    tx: server_tx,
+                },
+            ));
+            inner.accept_wait.wake_all();
+            drop(inner);
+            self.inner.exclusive_access().state = SocketState::Stream { rx, tx };
+            return 0;
+        }
+    }
+    /// Send one whole message, blocking while the queue of the peer is full
+    fn send_dgram(&self, peer: &Weak<UPSafeCell<DgramQueue>>, buf: UserBuffer) -> isize {
+        if buf.len() > PIPE_BUFFER_SIZE {
+            return -1;
+        }
+        let mut msg = Vec::with_capacity(buf.len());
+        for part in buf.buffers.iter() {
+            msg.extend_from_slice(part);
+        }
+        loop {
+            let Some(queue) = peer.upgrade() else {
+                return EPIPE;
+            };
+            let mut inner = queue.exclusive_access();
+            if inner.msgs.len() < UNIX_DGRAM_MAX_MSGS {
+                let len = msg.len();
+                inner.msgs.push_back(msg);
+                inner.read_wait.wake_all();
+                return len as isize;
+            }
+            if self.nonblock {
+                return EAGAIN;
+            }
+            inner.write_wait.add_current();
+            drop(inner);
+            drop(queue);
+            block_current_and_run_next();
+            if current_signal_pending() {
+                return EINTR;
+            }
+        }
+    }
+    /// Receive one message, the part not fitting in `buf` is discarded
+    fn recv_dgram(&self, queue: &UPSafeCell<DgramQueue>, mut buf: UserBuffer) -> isize {
+        loop {
+            let mut inner = queue.exclusive_access();
+            if let Some(msg) = inner.msgs.pop_front() {
+                inner.write_wait.wake_all();
+                drop(inner);
+                let mut copied = 0;
+                for part in buf.buffers.iter_mut() {
+                    let len = part.len().min(msg.len() - copied);
+                    part[..len].copy_from_slice(&msg[copied..copied + len]);
+                    copied += len;
+                }
+                return copied as isize;
+            }
+            if self.nonblock {
+                return EAGAIN;
+            }
+            inner.read_wait.add_current();
+            drop(inner);
+            block_current_and_run_next();
+            if current_signal_pending() {
+                return EINTR;
+            }
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // blocked peers must notice that we are gone
+        let inner = self.inner.exclusive_access();
+        if let Some(queue) = &inner.dgram_rx {
+            queue.exclusive_access().write_wait.wake_all();
+        }
+        if let SocketState::Listening(listener) = &inner.state {
+            listener.exclusive_access().connect_wait.wake_all();
+        }
+    }
+}
+
+impl File for Socket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> isize {
+        let inner = self.inner.exclusive_access();
+        match &inner.state {
+            SocketState::Stream { rx, .. } => {
+                let rx = rx.clone();
+                drop(inner);
+                rx.read(buf)
+            }
+            SocketState::Listening(_) => -1,
+            _ => {
+                let queue = inner.dgram_rx.clone();
+                drop(inner);
+                match queue {
+                    Some(queue) => self.recv_dgram(&queue, buf),
+                    None => -1,
+                }
+            }
+        }
+    }
+    fn write(&self, buf: UserBuffer) -> isize {
+        let inner = self.inner.exclusive_access();
+        match &inner.state {
+            SocketState::Stream { tx, .. } => {
+                let tx = tx.clone();
+                drop(inner);
+                tx.write(buf)
+            }
+            SocketState::Datagram(peer) => {
+                let peer = peer.clone();
+                drop(inner);
+                self.send_dgram(&peer, buf)
+            }
+            _ => -1,
+        }
+    }
+
+    fn poll(&self) -> PollEvents {
+        let inner = self.inner.exclusive_access();
+        match &inner.state {
+            SocketState::Stream { rx, tx } => rx.poll() | tx.poll(),
+            SocketState::Listening(listener) => {
+                if listener.exclusive_access().pending.is_empty() {
+                    PollEvents::empty()
+                } else {
+                    PollEvents::IN
+                }
+            }
+            state => {
+                let mut events = PollEvents::empty();
+                if let Some(queue) = &inner.dgram_rx {
+                    if !queue.exclusive_access().msgs.is_empty() {
+                        events |= PollEvents::IN;
+                    }
+                }
+                match state {
+                    SocketState::Datagram(peer) => match peer.upgrade() {
+                        Some(queue)
+                            if queue.exclusive_access().msgs.len() < UNIX_DGRAM_MAX_MSGS =>
+                        {
+                            events |= PollEvents::OUT;
+                        }
+                        Some(_) => {}
+                        None => events |= PollEvents::ERR,
+                    },
+                    // an unconnected stream socket has nothing to wait for
+                    _ if self.type_ == SocketType::Stream => events |= PollEvents::HUP,
+                    _ => {}
+                }
+                events
+            }
+        }
+    }
+    fn add_poll_waiter(&self) {
+        let inner = self.inner.exclusive_access();
+        match &inner.state {
+            SocketState::Stream { rx, tx } => {
+                rx.add_poll_waiter();
+                tx.add_poll_waiter();
+            }
+            SocketState::Listening(listener) => {
+                listener.exclusive_access().accept_wait.add_current()
+            }
+            state => {
+                if let Some(queue) = &inner.dgram_rx {
+                    queue.exclusive_access().read_wait.add_current();
+                }
+                if let SocketState::Datagram(peer) = state {
+                    if let Some(queue) = peer.upgrade() {
+                        queue.exclusive_access().write_wait.add_current();
+                    }
+                }
+            }
+        }
+    }
+}
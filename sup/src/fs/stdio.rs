@@ -1,13 +1,21 @@
-//!Stdin & Stdout
-use super::File;
+//!Stdin & Stdout, both ends of the console terminal
+use super::{File, LineDiscipline, PollEvents, tty_ioctl, tty_read};
 use crate::mm::UserBuffer;
-use crate::sbi::scan;
-use crate::task::suspend_current_and_run_next;
+use crate::sbi::write_bytes;
+use crate::sync::UPSafeCell;
+use alloc::vec::Vec;
+use lazy_static::*;
 ///Standard input
 pub struct Stdin;
 ///Standard output
 pub struct Stdout;
 
+lazy_static! {
+    /// line discipline of the UART console
+    pub static ref CONSOLE_TTY: UPSafeCell<LineDiscipline> =
+        unsafe { UPSafeCell::new(LineDiscipline::new()) };
+}
+
 impl File for Stdin {
     fn readable(&self) -> bool {
         true
@@ -15,27 +23,25 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-        // busy loop
-        let mut c: u8;
-        loop {
-            c = scan();
-            if c == 0 {
-                suspend_current_and_run_next();
-                continue;
-            } else {
-                break;
-            }
-        }
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(c);
-        }
-        1
+    fn read(&self, user_buf: UserBuffer) -> isize {
+        tty_read(&CONSOLE_TTY, user_buf, false)
     }
-    fn write(&self, _user_buf: UserBuffer) -> usize {
+    fn write(&self, _user_buf: UserBuffer) -> isize {
         panic!("Cannot write to stdin!");
     }
+    fn poll(&self) -> PollEvents {
+        if CONSOLE_TTY.exclusive_access().has_input() {
+            PollEvents::IN
+        } else {
+            PollEvents::empty()
+        }
+    }
+    fn add_poll_waiter(&self) {
+        CONSOLE_TTY.exclusive_access().read_wait.add_current();
+    }
+    fn ioctl(&self, request: usize, arg: usize) -> isize {
+        tty_ioctl(&CONSOLE_TTY, request, arg)
+    }
 }
 
 impl File for Stdout {
@@ -45,13 +51,20 @@ impl File for Stdout {
     fn writable(&self) -> bool {
         true
     }
-    fn read(&self, _user_buf: UserBuffer) -> usize {
+    fn read(&self, _user_buf: UserBuffer) -> isize {
         panic!("Cannot read from stdout!");
     }
-    fn write(&self, user_buf: UserBuffer) -> usize {
+    fn write(&self, user_buf: UserBuffer) -> isize {
+        let mut out = Vec::with_capacity(user_buf.len());
+        let tty = CONSOLE_TTY.exclusive_access();
         for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(*buffer).unwrap());
+            tty.output(buffer, &mut out);
         }
-        user_buf.len()
+        drop(tty);
+        write_bytes(&out);
+        user_buf.len() as isize
+    }
+    fn ioctl(&self, request: usize, arg: usize) -> isize {
+        tty_ioctl(&CONSOLE_TTY, request, arg)
     }
 }
@@ -0,0 +1,254 @@
+//! tmpfs: files kept in memory, gone at power off. File data is in whole
+//! pages from the frame allocator, off the kernel heap, and only names and
+//! inodes are on the heap. Nothing here sleeps, so cells are enough.
+use super::vfs::{FileSystemType, InodeType, Stat, VfsInode};
+use super::EINVAL;
+use crate::config::{PAGE_SIZE, TMPFS_DEFAULT_INODES, TMPFS_DEFAULT_SIZE};
+use crate::mm::{FrameTracker, frame_alloc};
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+pub struct TmpFsType;
+
+impl FileSystemType for TmpFsType {
+    fn name(&self) -> &'static str {
+        "tmpfs"
+    }
+    /// `source` is only a name for the mount table. `data` may hold
+    /// `size=<bytes>` and `nr_inodes=<n>` separated by commas, sizes may end
+    /// in `k`, `m` or `g`.
+    fn mount(&self, _source: &str, data: &str) -> Result<Arc<dyn VfsInode>, isize> {
+        let mut size = TMPFS_DEFAULT_SIZE;
+        let mut max_inodes = TMPFS_DEFAULT_INODES;
+        for option in data.split(',').filter(|option| !option.is_empty()) {
+            match option.split_once('=') {
+                Some(("size", value)) => size = parse_size(value).ok_or(EINVAL)?,
+                Some(("nr_inodes", value)) => max_inodes = parse_size(value).ok_or(EINVAL)?,
+                _ => return Err(EINVAL),
+            }
+        }
+        let fs = Arc::new(TmpFs {
+            max_pages: size.div_ceil(PAGE_SIZE),
+            max_inodes,
+            usage: unsafe { UPSafeCell::new(Usage { pages: 0, inodes: 0, next_ino: 1 }) },
+        });
+        let root: Arc<dyn VfsInode> = TmpInode::new(&fs, InodeType::Directory).ok_or(EINVAL)?;
+        Ok(root)
+    }
+}
+
+/// `123`, `64k`, `16M` and the like
+fn parse_size(value: &str) -> Option<usize> {
+    let (digits, shift) = match value.as_bytes().last()? {
+        b'k' | b'K' => (&value[..value.len() - 1], 10),
+        b'm' | b'M' => (&value[..value.len() - 1], 20),
+        b'g' | b'G' => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(1 << shift)
+}
+
+/// One mounted tmpfs, what its limits are and how much of them is taken
+struct TmpFs {
+    max_pages: usize,
+    max_inodes: usize,
+    usage: UPSafeCell<Usage>,
+}
+
+struct Usage {
+    pages: usize,
+    inodes: usize,
+    next_ino: usize,
+}
+
+impl TmpFs {
+    /// A zeroed page counted against the size limit
+    fn alloc_page(&self) -> Option<FrameTracker> {
+        let mut usage = self.usage.exclusive_access();
+        if usage.pages >= self.max_pages {
+            return None;
+        }
+        let frame = frame_alloc()?;
+        usage.pages += 1;
+        Some(frame)
+    }
+    fn release_pages(&self, pages: usize) {
+        self.usage.exclusive_access().pages -= pages;
+    }
+}
+
+pub struct TmpInode {
+    fs: Arc<TmpFs>,
+    ino: usize,
+    type_: InodeType,
+    inner: UPSafeCell<TmpInodeInner>,
+}
+
+struct TmpInodeInner {
+    /// bytes of a file
+    size: usize,
+    /// data of a file, page `i` holds bytes from `i * PAGE_SIZE`
+    pages: Vec<FrameTracker>,
+    /// entries of a directory
+    entries: BTreeMap<String, Arc<TmpInode>>,
+}
+
+impl TmpInode {
+    /// None once the inode limit is reached
+    fn new(fs: &Arc<TmpFs>, type_: InodeType) -> Option<Arc<Self>> {
+        let mut usage = fs.usage.exclusive_access();
+        if usage.inodes >= fs.max_inodes {
+            return None;
+        }
+        usage.inodes += 1;
+        let ino = usage.next_ino;
+        usage.next_ino += 1;
+        Some(Arc::new(Self {
+            fs: fs.clone(),
+            ino,
+            type_,
+            inner: unsafe {
+                UPSafeCell::new(TmpInodeInner {
+                    size: 0,
+                    pages: Vec::new(),
+                    entries: BTreeMap::new(),
+                })
+            },
+        }))
+    }
+    /// Whether `other` is this directory or somewhere below it
+    fn holds(&self, other: &TmpInode) -> bool {
+        self.ino == other.ino
+            || self
+                .inner
+                .exclusive_access()
+                .entries
+                .values()
+                .any(|entry| entry.type_ == InodeType::Directory && entry.holds(other))
+    }
+}
+
+impl Drop for TmpInode {
+    fn drop(&mut self) {
+        let pages = self.inner.exclusive_access().pages.len();
+        self.fs.release_pages(pages);
+        self.fs.usage.exclusive_access().inodes -= 1;
+    }
+}
+
+impl VfsInode for TmpInode {
+    fn lookup(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        let inode = self.inner.exclusive_access().entries.get(name)?.clone();
+        Some(inode)
+    }
+    fn create(&self, name: &str, type_: InodeType) -> Option<Arc<dyn VfsInode>> {
+        if self.type_ != InodeType::Directory
+            || self.inner.exclusive_access().entries.contains_key(name)
+        {
+            return None;
+        }
+        let inode = TmpInode::new(&self.fs, type_)?;
+        self.inner
+            .exclusive_access()
+            .entries
+            .insert(name.to_string(), inode.clone());
+        Some(inode)
+    }
+    fn readdir(&self) -> Vec<String> {
+        self.inner.exclusive_access().entries.keys().cloned().collect()
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let inner = self.inner.exclusive_access();
+        let end = inner.size.min(offset.saturating_add(buf.len()));
+        let mut pos = offset;
+        while pos < end {
+            let in_page = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - in_page).min(end - pos);
+            let page = inner.pages[pos / PAGE_SIZE].ppn.get_bytes_array();
+            buf[pos - offset..pos - offset + len].copy_from_slice(&page[in_page..in_page + len]);
+            pos += len;
+        }
+        end.saturating_sub(offset)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let end = offset.saturating_add(buf.len());
+        let had_pages = inner.pages.len();
+        // pages of a hole before `offset` are taken too, they read as zeros
+        while inner.pages.len() * PAGE_SIZE < end {
+            match self.fs.alloc_page() {
+                Some(page) => inner.pages.push(page),
+                None => break,
+            }
+        }
+        let end = end.min(inner.pages.len() * PAGE_SIZE);
+        if end <= offset {
+            // nothing lands, so the hole is not kept either
+            let added = inner.pages.len() - had_pages;
+            inner.pages.truncate(had_pages);
+            self.fs.release_pages(added);
+            return 0;
+        }
+        let mut pos = offset;
+        while pos < end {
+            let in_page = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - in_page).min(end - pos);
+            let page = inner.pages[pos / PAGE_SIZE].ppn.get_bytes_array();
+            page[in_page..in_page + len].copy_from_slice(&buf[pos - offset..pos - offset + len]);
+            pos += len;
+        }
+        inner.size = inner.size.max(end);
+        end - offset
+    }
+    fn stat(&self) -> Stat {
+        Stat {
+            dev: Arc::as_ptr(&self.fs) as usize,
+            ino: self.ino,
+            type_: self.type_,
+            size: self.inner.exclusive_access().size,
+        }
+    }
+    /// The inode lives on while a file is open on it
+    fn unlink(&self, name: &str) -> bool {
+        // dropped after the entries are let go, it may free a whole tree
+        let removed = self.inner.exclusive_access().entries.remove(name);
+        removed.is_some()
+    }
+    fn truncate(&self) {
+        let pages = {
+            let mut inner = self.inner.exclusive_access();
+            inner.size = 0;
+            core::mem::take(&mut inner.pages)
+        };
+        self.fs.release_pages(pages.len());
+    }
+    fn rename(&self, old_name: &str, new_parent: &Arc<dyn VfsInode>, new_name: &str) -> bool {
+        let Some(new_parent) = (new_parent.as_ref() as &dyn Any).downcast_ref::<TmpInode>() else {
+            return false;
+        };
+        if !Arc::ptr_eq(&new_parent.fs, &self.fs) || new_parent.type_ != InodeType::Directory {
+            return false;
+        }
+        let Some(inode) = self.inner.exclusive_access().entries.get(old_name).cloned() else {
+            return false;
+        };
+        if new_parent.inner.exclusive_access().entries.contains_key(new_name) {
+            return false;
+        }
+        // a directory moved below itself would be cut off from the root
+        if inode.type_ == InodeType::Directory && inode.holds(new_parent) {
+            return false;
+        }
+        self.inner.exclusive_access().entries.remove(old_name);
+        new_parent
+            .inner
+            .exclusive_access()
+            .entries
+            .insert(new_name.to_string(), inode);
+        true
+    }
+}
@@ -0,0 +1,333 @@
+//! Terminal line discipline between a character device and the fd table
+use super::{EAGAIN, EINTR, EINVAL, ENOTTY};
+use crate::config::{TTY_BUFFER_SIZE, TTY_MAX_CANON};
+use crate::mm::{UserBuffer, translated_ref, translated_refmut};
+use crate::sync::{UPSafeCell, WaitQueue};
+use crate::task::{
+    SignalFlags, block_current_and_run_next, current_signal_pending, current_user_token,
+    send_signal,
+};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+/// set after output drains, output is never queued here
+pub const TCSETSW: usize = 0x5403;
+/// set and discard pending input
+pub const TCSETSF: usize = 0x5404;
+pub const TIOCGPGRP: usize = 0x540f;
+pub const TIOCSPGRP: usize = 0x5410;
+pub const TIOCGWINSZ: usize = 0x5413;
+
+pub const ICRNL: u32 = 0o400;
+pub const OPOST: u32 = 0o1;
+pub const ONLCR: u32 = 0o4;
+pub const ISIG: u32 = 0o1;
+pub const ICANON: u32 = 0o2;
+pub const ECHO: u32 = 0o10;
+pub const ECHOE: u32 = 0o20;
+pub const ECHOK: u32 = 0o40;
+pub const ECHONL: u32 = 0o100;
+
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VERASE: usize = 2;
+pub const VKILL: usize = 3;
+pub const VEOF: usize = 4;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSUSP: usize = 10;
+pub const NCCS: usize = 19;
+
+/// `struct termios` as TCGETS and TCSETS see it.
+/// `c_cflag` and `c_cc[VTIME]` are kept but have no effect.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; NCCS],
+}
+
+impl Default for Termios {
+    fn default() -> Self {
+        let mut c_cc = [0u8; NCCS];
+        c_cc[VINTR] = 0x03;
+        c_cc[VQUIT] = 0x1c;
+        c_cc[VERASE] = 0x7f;
+        c_cc[VKILL] = 0x15;
+        c_cc[VEOF] = 0x04;
+        c_cc[VTIME] = 0;
+        c_cc[VMIN] = 1;
+        c_cc[VSUSP] = 0x1a;
+        Self {
+            c_iflag: ICRNL,
+            c_oflag: OPOST | ONLCR,
+            // B38400 | CS8 | CREAD
+            c_cflag: 0o17 | 0o60 | 0o200,
+            c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK,
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
+
+/// `struct winsize` of TIOCGWINSZ
+#[repr(C)]
+pub struct WinSize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
+
+pub struct LineDiscipline {
+    pub termios: Termios,
+    /// line being edited in canonical mode
+    line: Vec<u8>,
+    /// input handed to readers; in canonical mode each chunk is a line
+    /// and an empty chunk is an end of file
+    ready: VecDeque<Vec<u8>>,
+    ready_len: usize,
+    /// process that receives the signal characters
+    pub foreground: Option<usize>,
+    /// the device went away, readers get end of file
+    hung_up: bool,
+    pub read_wait: WaitQueue,
+}
+
+impl LineDiscipline {
+    pub fn new() -> Self {
+        Self {
+            termios: Termios::default(),
+            line: Vec::new(),
+            ready: VecDeque::new(),
+            ready_len: 0,
+            foreground: None,
+            hung_up: false,
+            read_wait: WaitQueue::new(),
+        }
+    }
+    fn lflag(&self, flag: u32) -> bool {
+        self.termios.c_lflag & flag != 0
+    }
+    fn push_ready(&mut self, chunk: Vec<u8>) {
+        self.ready_len += chunk.len();
+        self.ready.push_back(chunk);
+        self.read_wait.wake_all();
+    }
+    fn flush_line(&mut self) {
+        if !self.line.is_empty() {
+            let line = core::mem::take(&mut self.line);
+            self.push_ready(line);
+        }
+    }
+    /// Handle one received character, what has to be echoed goes to `echo`.
+    /// Return the signal for the foreground process if `c` raises one.
+    pub fn receive(&mut self, mut c: u8, echo: &mut Vec<u8>) -> Option<SignalFlags> {
+        let cc = self.termios.c_cc;
+        if c == b'\r' && self.termios.c_iflag & ICRNL != 0 {
+            c = b'\n';
+        }
+        if self.lflag(ISIG) && c != 0 {
+            let signal = if c == cc[VINTR] {
+                Some(SignalFlags::SIGINT)
+            } else if c == cc[VQUIT] {
+                Some(SignalFlags::SIGQUIT)
+            } else {
+                // VSUSP raises nothing: waitpid does not report stopped
+                // children, so a job stopped from here could never be resumed
+                None
+            };
+            if signal.is_some() {
+                self.line.clear();
+                if self.lflag(ECHO) {
+                    echo.extend_from_slice(&[b'^', c + b'@', b'\n']);
+                }
+                return signal;
+            }
+        }
+        if !self.lflag(ICANON) {
+            if self.ready_len < TTY_BUFFER_SIZE {
+                self.push_ready(alloc::vec![c]);
+                if self.lflag(ECHO) {
+                    echo.push(c);
+                }
+            }
+            return None;
+        }
+        if c != 0 && c == cc[VERASE] {
+            if self.line.pop().is_some() && self.lflag(ECHO) {
+                if self.lflag(ECHOE) {
+                    echo.extend_from_slice(b"\x08 \x08");
+                } else {
+                    echo.push(c);
+                }
+            }
+        } else if c != 0 && c == cc[VKILL] {
+            if self.lflag(ECHO) && self.lflag(ECHOK) {
+                for _ in 0..self.line.len() {
+                    echo.extend_from_slice(b"\x08 \x08");
+                }
+            }
+            self.line.clear();
+        } else if c != 0 && c == cc[VEOF] {
+            // an empty line reads as end of file
+            let line = core::mem::take(&mut self.line);
+            self.push_ready(line);
+        } else if c == b'\n' {
+            if self.ready_len + self.line.len() < TTY_BUFFER_SIZE {
+                self.line.push(c);
+                self.flush_line();
+            }
+            if self.lflag(ECHO) || self.lflag(ECHONL) {
+                echo.push(c);
+            }
+        } else if self.line.len() < TTY_MAX_CANON {
+            self.line.push(c);
+            if self.lflag(ECHO) {
+                echo.push(c);
+            }
+        }
+        None
+    }
+    /// Output processing of bytes written to the terminal
+    pub fn output(&self, buf: &[u8], out: &mut Vec<u8>) {
+        let onlcr = self.termios.c_oflag & (OPOST | ONLCR) == OPOST | ONLCR;
+        for &c in buf {
+            if c == b'\n' && onlcr {
+                out.push(b'\r');
+            }
+            out.push(c);
+        }
+    }
+    pub fn has_input(&self) -> bool {
+        !self.ready.is_empty()
+    }
+    pub fn hang_up(&mut self) {
+        self.hung_up = true;
+        self.read_wait.wake_all();
+    }
+    pub fn is_hung_up(&self) -> bool {
+        self.hung_up
+    }
+    /// Take at most `max` bytes of input, None if the reader has to wait.
+    /// Canonical mode returns at most one line, raw mode waits for VMIN bytes.
+    fn take_input(&mut self, max: usize) -> Option<Vec<u8>> {
+        if self.hung_up && self.ready.is_empty() {
+            return Some(Vec::new());
+        }
+        if self.lflag(ICANON) {
+            let mut line = self.ready.pop_front()?;
+            if line.len() > max {
+                self.ready.push_front(line.split_off(max));
+            }
+            self.ready_len -= line.len();
+            return Some(line);
+        }
+        let min = (self.termios.c_cc[VMIN] as usize).min(max);
+        if self.ready_len < min && !self.hung_up {
+            return None;
+        }
+        let mut taken = Vec::new();
+        while taken.len() < max {
+            let Some(mut chunk) = self.ready.pop_front() else {
+                break;
+            };
+            let room = max - taken.len();
+            if chunk.len() > room {
+                self.ready.push_front(chunk.split_off(room));
+            }
+            self.ready_len -= chunk.len();
+            taken.extend_from_slice(&chunk);
+        }
+        Some(taken)
+    }
+    fn set_termios(&mut self, termios: Termios, flush: bool) {
+        if flush {
+            self.line.clear();
+            self.ready.clear();
+            self.ready_len = 0;
+        }
+        self.termios = termios;
+        if !self.lflag(ICANON) {
+            // a half edited line becomes readable input
+            self.flush_line();
+        }
+        self.read_wait.wake_all();
+    }
+}
+
+/// Read from `tty`, blocking unless `nonblock`
+pub fn tty_read(tty: &UPSafeCell<LineDiscipline>, buf: UserBuffer, nonblock: bool) -> isize {
+    let input = loop {
+        let mut inner = tty.exclusive_access();
+        if let Some(input) = inner.take_input(buf.len()) {
+            break input;
+        }
+        if nonblock {
+            return EAGAIN;
+        }
+        if current_signal_pending() {
+            return EINTR;
+        }
+        inner.read_wait.add_current();
+        drop(inner);
+        block_current_and_run_next();
+    };
+    for (byte, c) in buf.into_iter().zip(input.iter()) {
+        unsafe {
+            *byte = *c;
+        }
+    }
+    input.len() as isize
+}
+
+/// Feed a character received by the device behind `tty`, the echo goes to `echo`
+pub fn tty_receive(tty: &UPSafeCell<LineDiscipline>, c: u8, echo: &mut Vec<u8>) {
+    let mut inner = tty.exclusive_access();
+    let mut raw_echo = Vec::new();
+    let signal = inner.receive(c, &mut raw_echo);
+    inner.output(&raw_echo, echo);
+    let foreground = inner.foreground;
+    drop(inner);
+    if let (Some(signal), Some(pid)) = (signal, foreground) {
+        send_signal(pid, signal);
+    }
+}
+
+/// Terminal ioctls, `arg` points into the current address space
+pub fn tty_ioctl(tty: &UPSafeCell<LineDiscipline>, request: usize, arg: usize) -> isize {
+    let token = current_user_token();
+    let mut inner = tty.exclusive_access();
+    match request {
+        TCGETS => *translated_refmut(token, arg as *mut Termios) = inner.termios,
+        TCSETS | TCSETSW | TCSETSF => {
+            let termios = *translated_ref(token, arg as *const Termios);
+            inner.set_termios(termios, request == TCSETSF);
+        }
+        TIOCGPGRP => *translated_refmut(token, arg as *mut i32) = inner.foreground.map_or(0, |pid| pid as i32),
+        TIOCSPGRP => {
+            // initproc is pid 0 and never gets them, so 0 clears the foreground
+            let pid = *translated_ref(token, arg as *const i32);
+            if pid < 0 {
+                return EINVAL;
+            }
+            inner.foreground = (pid > 0).then_some(pid as usize);
+        }
+        TIOCGWINSZ => {
+            *translated_refmut(token, arg as *mut WinSize) = WinSize {
+                ws_row: 24,
+                ws_col: 80,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            }
+        }
+        _ => return ENOTTY,
+    }
+    0
+}
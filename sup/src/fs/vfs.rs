@@ -0,0 +1,238 @@
+//! The virtual file system: inodes of any file system behind `VfsInode`, and
+//! the mount table path resolution goes through. Paths are taken from the
+//! root, a leading `/` is allowed.
+use super::easyfs::{EasyFsType, open_disk};
+use super::tmpfs::TmpFsType;
+use super::{EBUSY, EINVAL, ENODEV, ENOENT, ENOTDIR};
+use crate::cmdline::kernel_config;
+use crate::drivers::BLOCK_DEVICE;
+use crate::sync::UPSafeCell;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use lazy_static::*;
+use log::warn;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InodeType {
+    File,
+    Directory,
+    /// named pipe, the data goes through a pipe buffer
+    Fifo,
+    /// address of a bound unix-domain socket
+    Socket,
+}
+
+/// What `stat` tells about an inode
+#[derive(Clone, Copy)]
+pub struct Stat {
+    /// the file system instance, `dev` and `ino` together name one inode
+    pub dev: usize,
+    pub ino: usize,
+    pub type_: InodeType,
+    /// bytes of a file
+    pub size: usize,
+}
+
+/// An inode of some file system, names are single path components
+pub trait VfsInode: Any + Send + Sync {
+    /// The entry `name` of this directory
+    fn lookup(&self, name: &str) -> Option<Arc<dyn VfsInode>>;
+    /// Make the entry `name` in this directory, None if it exists
+    fn create(&self, name: &str, type_: InodeType) -> Option<Arc<dyn VfsInode>>;
+    /// Names in this directory, without `.` and `..`
+    fn readdir(&self) -> Vec<String>;
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize;
+    /// Bytes written, fewer if the file system is full
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize;
+    fn stat(&self) -> Stat;
+    /// Remove the entry `name`, a directory goes with everything in it
+    fn unlink(&self, name: &str) -> bool;
+    /// Drop the data of this file
+    fn truncate(&self);
+    /// Move the entry `old_name` to `new_name` in `new_parent`, which must be
+    /// of the same file system. Fails if `new_name` exists.
+    fn rename(&self, old_name: &str, new_parent: &Arc<dyn VfsInode>, new_name: &str) -> bool;
+}
+
+/// A kind of file system `sys_mount` can make
+pub trait FileSystemType: Sync {
+    /// The type given to `sys_mount`, like `easyfs`
+    fn name(&self) -> &'static str;
+    /// Mount from `source` with the options `data`, return the root or a negative errno
+    fn mount(&self, source: &str, data: &str) -> Result<Arc<dyn VfsInode>, isize>;
+}
+
+/// Types by name, a new file system only needs a line here
+static FILE_SYSTEM_TYPES: &[&dyn FileSystemType] = &[&EasyFsType, &TmpFsType];
+
+/// A file system mounted on a directory
+pub struct Mount {
+    /// components of the mount point, none for the root
+    pub path: Vec<String>,
+    pub source: String,
+    pub fs_type: &'static str,
+    pub root: Arc<dyn VfsInode>,
+}
+
+lazy_static! {
+    /// Mounted file systems, the root disk first
+    pub static ref MOUNTS: UPSafeCell<Vec<Mount>> = unsafe {
+        UPSafeCell::new(vec![Mount {
+            path: Vec::new(),
+            source: String::from("/dev/root"),
+            fs_type: EasyFsType.name(),
+            root: open_disk(BLOCK_DEVICE.clone()).expect("No easy-fs on the root disk"),
+        }])
+    };
+}
+
+/// Components of `path` without `.` and `..`, `..` of the root is the root
+fn normalize(path: &str) -> Vec<&str> {
+    let mut components = Vec::new();
+    for name in path.split('/') {
+        match name {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    components
+}
+
+/// Root of the innermost mount holding `components`, and how many of them it covers
+fn mount_of(components: &[&str]) -> (Arc<dyn VfsInode>, usize) {
+    let mounts = MOUNTS.exclusive_access();
+    let mount = mounts
+        .iter()
+        .filter(|mount| {
+            mount.path.len() <= components.len()
+                && mount.path.iter().zip(components).all(|(a, b)| a == b)
+        })
+        .max_by_key(|mount| mount.path.len())
+        .unwrap();
+    (mount.root.clone(), mount.path.len())
+}
+
+/// The inode at `path`, crossing into mounted file systems on the way
+pub fn resolve(path: &str) -> Option<Arc<dyn VfsInode>> {
+    let components = normalize(path);
+    let (mut inode, covered) = mount_of(&components);
+    for name in &components[covered..] {
+        inode = inode.lookup(name)?;
+    }
+    Some(inode)
+}
+
+/// The directory holding `path` and the last name of it. A mount point is
+/// no entry of its parent, so it is refused, as is the root.
+pub fn resolve_parent(path: &str) -> Option<(Arc<dyn VfsInode>, String)> {
+    let components = normalize(path);
+    let (name, dir) = components.split_last()?;
+    if is_busy(&components) {
+        return None;
+    }
+    let (mut inode, covered) = mount_of(dir);
+    for name in &dir[covered..] {
+        inode = inode.lookup(name)?;
+    }
+    (inode.stat().type_ == InodeType::Directory).then(|| (inode, name.to_string()))
+}
+
+/// Whether something is mounted at `components` or below
+fn is_busy(components: &[&str]) -> bool {
+    MOUNTS.exclusive_access().iter().any(|mount| {
+        mount.path.len() >= components.len()
+            && components.iter().zip(&mount.path).all(|(a, b)| a == b)
+    })
+}
+
+/// Mount a file system of `fs_type` from `source` on the directory `target`,
+/// 0 or a negative errno
+pub fn mount(source: &str, target: &str, fs_type: &str, data: &str) -> isize {
+    let Some(fs_type) = FILE_SYSTEM_TYPES.iter().find(|type_| type_.name() == fs_type) else {
+        return ENODEV;
+    };
+    let Some(dir) = resolve(target) else {
+        return ENOENT;
+    };
+    if dir.stat().type_ != InodeType::Directory {
+        return ENOTDIR;
+    }
+    let path: Vec<String> = normalize(target).into_iter().map(String::from).collect();
+    if MOUNTS.exclusive_access().iter().any(|mount| mount.path == path) {
+        return EBUSY;
+    }
+    // mounting may do disk I/O, which sleeps, so the table is not held
+    let root = match fs_type.mount(source, data) {
+        Ok(root) => root,
+        Err(errno) => return errno,
+    };
+    let mut mounts = MOUNTS.exclusive_access();
+    if mounts.iter().any(|mount| mount.path == path) {
+        return EBUSY;
+    }
+    mounts.push(Mount {
+        path,
+        source: source.to_string(),
+        fs_type: fs_type.name(),
+        root,
+    });
+    0
+}
+
+/// Unmount what is mounted at `target`, 0 or a negative errno. Files open on
+/// it stay usable, they hold what they need.
+pub fn umount(target: &str) -> isize {
+    let components = normalize(target);
+    let mut mounts = MOUNTS.exclusive_access();
+    let Some(index) = mounts.iter().position(|mount| mount.path == components) else {
+        return EINVAL;
+    };
+    let below = |mount: &Mount| {
+        mount.path.len() > components.len()
+            && components.iter().zip(&mount.path).all(|(a, b)| a == b)
+    };
+    if components.is_empty() || mounts.iter().any(below) {
+        return EBUSY;
+    }
+    mounts.remove(index);
+    0
+}
+
+/// Mount a tmpfs on `/tmp`, made on the root disk if it is not there
+pub fn init() {
+    let made = resolve("tmp")
+        .or_else(|| resolve("")?.create("tmp", InodeType::Directory))
+        .is_some();
+    if !made {
+        warn!("[kernel] cannot make /tmp");
+        return;
+    }
+    let data = kernel_config()
+        .tmpfs_size
+        .map(|size| format!("size={}", size))
+        .unwrap_or_default();
+    let mut result = mount("tmpfs", "tmp", "tmpfs", &data);
+    if result == EINVAL && !data.is_empty() {
+        warn!("[kernel] bad tmpfs_size, /tmp gets the default");
+        result = mount("tmpfs", "tmp", "tmpfs", "");
+    }
+    if result < 0 {
+        warn!("[kernel] cannot mount /tmp: {}", result);
+    }
+}
+
+/// List the mount table, like /proc/mounts
+pub fn list_mounts() {
+    println!("/**** MOUNTS ****");
+    for mount in MOUNTS.exclusive_access().iter() {
+        println!("{} on /{} type {}", mount.source, mount.path.join("/"), mount.fs_type);
+    }
+    println!("****************/");
+}
@@ -13,5 +13,6 @@ fn panic(info: &PanicInfo) -> ! {
     } else {
         println!("Panicked: {}", info.message());
     }
-    shutdown(false)
+    // the host tells a panic from a clean power off by the exit status
+    shutdown(true)
 }
\ No newline at end of file
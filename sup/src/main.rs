@@ -5,11 +5,15 @@
 
 extern crate alloc;
 
+use cmdline::kernel_config;
 use log::info;
 
 #[macro_use]
 mod sbi;
 mod sync;
+mod board;
+mod cmdline;
+mod fdt;
 mod config;
 pub mod lang_items;
 mod logging;
@@ -21,27 +25,53 @@ pub mod task;
 pub mod timer;
 pub mod fs;
 mod drivers;
+mod random;
 
+core::arch::global_asm!(include_str!("entry.asm"));
 core::arch::global_asm!(include_str!("link_app.S"));
 
+/// Entered from `entry.asm` on the boot stack, with what the SBI passes in a0 and a1
 #[unsafe(no_mangle)]
-#[unsafe(link_section = ".text.entry")]
-pub extern "C" fn _start() -> ! {
+pub fn rust_main(_hartid: usize, dtb: usize) -> ! {
     clear_bss();
+    board::init(dtb);
     logging::init();
     sbi::init_uart();
     info!("[kernel] Switched to Supervisor Mode");
+    mm::init_heap();
+    cmdline::init();
     mm::init();
+    info!("[kernel] memory ends at {:#x}", board::board().memory_end);
     mm::remap_test();
     trap::init();
     trap::enable_timer_interrupt();
-    timer::set_next_trigger();
-    fs::list_apps();
+    drivers::init();
+    random::init();
+    fs::init();
+    trap::enable_external_interrupt();
+    if cmdline::selftest() {
+        selftest();
+    }
+    if !kernel_config().quiet {
+        drivers::list_devices();
+        fs::list_mounts();
+        fs::list_apps();
+    }
     task::add_initproc();
     task::run_tasks();
     panic!("Unreachable in rust_main!");
 }
 
+/// `selftest` on the command line: a failing test panics, which powers off with an error
+fn selftest() -> ! {
+    mm::heap_test();
+    mm::frame_allocator_test();
+    mm::remap_test();
+    drivers::block::block_device_test();
+    info!("[kernel] selftest passed");
+    sbi::shutdown(false)
+}
+
 fn clear_bss() {
     unsafe extern "C" {
         safe fn sbss();
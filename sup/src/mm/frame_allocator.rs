@@ -1,4 +1,5 @@
-use crate::{config::*, println};
+use crate::board::board;
+use crate::println;
 use alloc::vec::Vec;
 
 use crate::sync::UPSafeCell;
@@ -13,7 +14,7 @@ pub fn init_frame() {
     }
     FRAME_ALLOCATOR.0.exclusive_access().init(
         PhysAddr::from(ekernel as usize).ceil(),
-        PhysAddr::from(MEMORY_END).floor(),
+        PhysAddr::from(board().memory_end).floor(),
     );
 }
 
@@ -7,6 +7,7 @@ use log::info;
 use log::warn;
 use riscv::register::satp;
 
+use crate::board::board;
 use crate::config::*;
 use crate::println;
 use crate::sync::UPSafeCell;
@@ -16,6 +17,7 @@ use super::frame_allocator::*;
 use super::page_table::PTEFlags;
 use super::page_table::PageTable;
 use super::page_table::PageTableEntry;
+use super::shm::ShmObject;
 use bitflags::*;
 
 unsafe extern "C" {
@@ -34,6 +36,8 @@ unsafe extern "C" {
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    /// permission of lazily mapped user stack pages, see `PT_GNU_STACK`
+    stack_perm: MapPermission,
 }
 
 impl MemorySet {
@@ -41,6 +45,7 @@ impl MemorySet {
         Self {
             page_table: PageTable::new(),
             areas: Vec::new(),
+            stack_perm: MapPermission::R | MapPermission::W | MapPermission::U,
         }
     }
     pub fn token(&self) -> usize {
@@ -68,13 +73,64 @@ impl MemorySet {
             self.areas.remove(idx);
         }
     }
+    /// Map `object` at the first free range from `SHM_BASE`, return its start address
+    pub fn map_shared(&mut self, object: Arc<ShmObject>) -> Option<usize> {
+        let pages = object.pages();
+        let mut start_vpn = VirtAddr::from(SHM_BASE).floor();
+        while let Some(area) = self.areas.iter().find(|area| {
+            area.vpn_range.get_start().0 < start_vpn.0 + pages
+                && start_vpn < area.vpn_range.get_end()
+        }) {
+            start_vpn = area.vpn_range.get_end();
+        }
+        let end_vpn = VirtPageNum(start_vpn.0 + pages);
+        if end_vpn > VirtAddr::from(USER_SPACE_END).floor() {
+            return None;
+        }
+        self.push(
+            MapArea {
+                vpn_range: VPNRange::new(start_vpn, end_vpn),
+                data_frames: BTreeMap::new(),
+                map_type: MapType::Shared,
+                map_perm: MapPermission::R | MapPermission::W | MapPermission::U,
+                shm: Some(object),
+            },
+            None,
+        );
+        Some(VirtAddr::from(start_vpn).into())
+    }
+    /// Unmap the shared area starting at `start_vpn`
+    pub fn unmap_shared(&mut self, start_vpn: VirtPageNum) -> bool {
+        let Some(idx) = self.areas.iter().position(|area| {
+            area.map_type == MapType::Shared && area.vpn_range.get_start() == start_vpn
+        }) else {
+            return false;
+        };
+        let mut area = self.areas.remove(idx);
+        area.unmap(&mut self.page_table);
+        true
+    }
     fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
         map_area.map(&mut self.page_table);
         if let Some(data) = data {
-            map_area.copy_data(&self.page_table, data);
+            map_area.copy_data(&self.page_table, 0, data);
         }
         self.areas.push(map_area);
     }
+    /// like `push`, but data starts at `offset` inside the first page
+    fn push_with_offset(&mut self, mut map_area: MapArea, offset: usize, data: &[u8]) {
+        map_area.map(&mut self.page_table);
+        map_area.copy_data(&self.page_table, offset, data);
+        self.areas.push(map_area);
+    }
+    /// write through the page table, the pages must be mapped already
+    fn write_bytes(&mut self, va: usize, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            let va = VirtAddr::from(va + i);
+            let ppn = self.page_table.translate(va.floor()).unwrap().ppn();
+            ppn.get_bytes_array()[va.page_offset()] = *byte;
+        }
+    }
     fn map_trampoline(&mut self) {
         self.page_table.map(
             VirtAddr::from(TRAMPOLINE).into(),
@@ -85,6 +141,7 @@ impl MemorySet {
     /// Without kernel stacks.
     pub fn new_kernel() -> Self {
         let mut memory_set = Self::new_bare();
+        let board = board();
         memory_set.map_trampoline();
         // map kernel sections
         println!(".text [{:#x}, {:#x})", stext as usize, etext as usize);
@@ -138,7 +195,7 @@ impl MemorySet {
         memory_set.push(
             MapArea::new(
                 (ekernel as usize).into(),
-                MEMORY_END.into(),
+                board.memory_end.into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
             ),
@@ -146,90 +203,187 @@ impl MemorySet {
         );
         println!("mapping mmio");
         memory_set.page_table.map(
-            VirtAddr::from(VIRT_UART0_BASE).into(),
-            PhysAddr::from(VIRT_UART0_BASE).into(),
-            PTEFlags::R | PTEFlags::W,
-        );
-        memory_set.page_table.map(
-            VirtAddr::from(VIRT_TEST_BASE).into(),
-            PhysAddr::from(VIRT_TEST_BASE).into(),
+            VirtAddr::from(board.uart).into(),
+            PhysAddr::from(board.uart).into(),
             PTEFlags::R | PTEFlags::W,
         );
         memory_set.page_table.map(
-            VirtAddr::from(MTIME - 0xFF8).into(),
-            PhysAddr::from(MTIME - 0xFF8).into(),
-            PTEFlags::R | PTEFlags::W,
-        );
-        memory_set.page_table.map(
-            VirtAddr::from(MTIMECMP).into(),
-            PhysAddr::from(MTIMECMP).into(),
+            VirtAddr::from(board.test).into(),
+            PhysAddr::from(board.test).into(),
             PTEFlags::R | PTEFlags::W,
         );
         println!("mapping memory-mapped registers");
-        for pair in MMIO {
+        for (base, size) in board.mmio() {
             memory_set.push(MapArea::new(
-                (*pair).0.into(),
-                ((*pair).0 + (*pair).1).into(),
+                base.into(),
+                (base + size).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
             ), None);
         }
-        println!("mapping machine area");
-        memory_set.push(
-            MapArea::new(
-                MACHINE_START.into(),
-                SUPERVISOR_START.into(),
-                MapType::Identical,
-                MapPermission::R | MapPermission::W,
-            ),
-            None,
-        );
         println!("mapping done");
         memory_set
     }
     /// Include sections in elf and trampoline and TrapContext and user stack,
     /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new_bare();
-        // map trampoline
-        memory_set.map_trampoline();
-        // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+    ///
+    /// The whole image is checked before anything is mapped, so a rejected
+    /// file never disturbs the address space of the caller.
+    pub fn from_elf(elf_data: &[u8]) -> Result<(Self, usize, usize), ElfError> {
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(ElfError::Malformed)?;
         let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if elf_header.pt1.magic != [0x7f, 0x45, 0x4c, 0x46] {
+            return Err(ElfError::BadMagic);
+        }
+        if elf_header.pt1.class() != xmas_elf::header::Class::SixtyFour {
+            return Err(ElfError::NotElf64);
+        }
+        if elf_header.pt1.data() != xmas_elf::header::Data::LittleEndian {
+            return Err(ElfError::NotLittleEndian);
+        }
+        // e_machine, read raw so we do not depend on the machine table of xmas_elf
+        let machine = u16::from_le_bytes([elf_data[18], elf_data[19]]);
+        if machine != EM_RISCV {
+            return Err(ElfError::BadMachine(machine));
+        }
+        let bias = match elf_header.pt2.type_().as_type() {
+            xmas_elf::header::Type::Executable => 0,
+            xmas_elf::header::Type::SharedObject => ELF_DYN_BASE,
+            _ => return Err(ElfError::BadType),
+        };
+        // xmas_elf slices the header table without checking it
+        let ph_offset = elf_header.pt2.ph_offset() as usize;
+        let ph_entry_size = elf_header.pt2.ph_entry_size() as usize;
         let ph_count = elf_header.pt2.ph_count();
-        let mut max_end_vpn = VirtPageNum(0);
+        if ph_offset == 0
+            || ph_offset % 8 != 0
+            || ph_entry_size != PH_ENTRY_SIZE
+            || ph_offset
+                .checked_add(ph_count as usize * ph_entry_size)
+                .is_none_or(|end| end > elf_data.len())
+        {
+            return Err(ElfError::BadProgramHeaders);
+        }
+
+        // first pass: validate every segment and remember what to load
+        let mut segments: Vec<ElfSegment> = Vec::new();
+        let mut dynamic: Option<&[u8]> = None;
+        let mut stack_perm = MapPermission::R | MapPermission::W | MapPermission::U;
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
-                let mut map_perm = MapPermission::U;
-                let ph_flags = ph.flags();
-                if ph_flags.is_read() {
-                    map_perm |= MapPermission::R;
-                }
-                if ph_flags.is_write() {
-                    map_perm |= MapPermission::W;
+            let ph = elf.program_header(i).map_err(ElfError::Malformed)?;
+            let ph_type = ph.get_type().map_err(ElfError::Malformed)?;
+            let offset = ph.offset() as usize;
+            let file_size = ph.file_size() as usize;
+            let file_end = offset
+                .checked_add(file_size)
+                .filter(|end| *end <= elf_data.len())
+                .ok_or(ElfError::BadSegment)?;
+            match ph_type {
+                xmas_elf::program::Type::Load => {}
+                xmas_elf::program::Type::Interp => return Err(ElfError::Interpreter),
+                xmas_elf::program::Type::Dynamic => {
+                    dynamic = Some(&elf_data[offset..file_end]);
+                    continue;
                 }
-                if ph_flags.is_execute() {
-                    map_perm |= MapPermission::X;
+                xmas_elf::program::Type::OsSpecific(PT_GNU_STACK) => {
+                    if ph.flags().is_execute() {
+                        stack_perm |= MapPermission::X;
+                    }
+                    continue;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                _ => continue,
             }
+            let vaddr = ph.virtual_addr() as usize;
+            let mem_size = ph.mem_size() as usize;
+            if file_size > mem_size {
+                return Err(ElfError::BadSegment);
+            }
+            let align = ph.align() as usize;
+            if align > 1 && (!align.is_power_of_two() || vaddr % align != offset % align) {
+                return Err(ElfError::BadAlignment);
+            }
+            if mem_size == 0 {
+                continue;
+            }
+            let start = vaddr.checked_add(bias).ok_or(ElfError::BadSegment)?;
+            let end = start
+                .checked_add(mem_size)
+                .filter(|end| *end <= USER_SPACE_END)
+                .ok_or(ElfError::BadSegment)?;
+            let start_va: VirtAddr = start.into();
+            let end_va: VirtAddr = end.into();
+            let (start_vpn, end_vpn) = (start_va.floor(), end_va.ceil());
+            // segments sharing a page would be mapped twice
+            if segments
+                .iter()
+                .any(|seg| seg.start_vpn < end_vpn && start_vpn < seg.end_vpn)
+            {
+                return Err(ElfError::SegmentOverlap);
+            }
+            let mut map_perm = MapPermission::U;
+            let ph_flags = ph.flags();
+            if ph_flags.is_read() {
+                map_perm |= MapPermission::R;
+            }
+            if ph_flags.is_write() {
+                map_perm |= MapPermission::W;
+            }
+            if ph_flags.is_execute() {
+                map_perm |= MapPermission::X;
+            }
+            segments.push(ElfSegment {
+                start_va,
+                end_va,
+                start_vpn,
+                end_vpn,
+                map_perm,
+                vaddr,
+                offset,
+                file_size,
+            });
+        }
+        let max_end_vpn = segments
+            .iter()
+            .map(|seg| seg.end_vpn)
+            .max()
+            .ok_or(ElfError::NoLoadableSegment)?;
+        let entry_point = (elf_header.pt2.entry_point() as usize).wrapping_add(bias);
+        if !segments.iter().any(|seg| {
+            seg.map_perm.contains(MapPermission::X)
+                && seg.start_va.0 <= entry_point
+                && entry_point < seg.end_va.0
+        }) {
+            return Err(ElfError::BadEntry);
         }
-        // map user stack with U flags
         let max_end_va: VirtAddr = max_end_vpn.into();
         let user_stack_bottom: usize = max_end_va.into();
         // we do not use guard page
         // user_stack_bottom += PAGE_SIZE;
         let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        if user_stack_top > USER_SPACE_END {
+            return Err(ElfError::BadSegment);
+        }
+        let relocations = match dynamic {
+            Some(dynamic) if bias != 0 => relative_relocations(elf_data, dynamic, &segments)?,
+            _ => Vec::new(),
+        };
+
+        // second pass: nothing below can fail on a bad image
+        let mut memory_set = Self::new_bare();
+        // map trampoline
+        memory_set.map_trampoline();
+        memory_set.stack_perm = stack_perm;
+        // map program headers of elf, with U flag
+        for seg in segments.iter() {
+            memory_set.push_with_offset(
+                MapArea::new(seg.start_va, seg.end_va, MapType::Framed, seg.map_perm),
+                seg.start_va.page_offset(),
+                &elf_data[seg.offset..seg.offset + seg.file_size],
+            );
+        }
+        for (r_offset, addend) in relocations {
+            memory_set.write_bytes(r_offset + bias, &(addend + bias).to_le_bytes());
+        }
 
         // we do not map all the stack in advance
         // info!("user stack top: {:#x}", user_stack_top);
@@ -253,21 +407,22 @@ impl MemorySet {
             ),
             None,
         );
-        (
-            memory_set,
-            user_stack_top,
-            elf.header.pt2.entry_point() as usize,
-        )
+        Ok((memory_set, user_stack_top, entry_point))
     }
     ///Clone a same `MemorySet`
     pub fn from_existed_user(user_space: &Self) -> Self {
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
+        memory_set.stack_perm = user_space.stack_perm;
         // copy data sections/trap_context/user_stack
         for area in user_space.areas.iter() {
             let new_area = MapArea::from_another(area);
             memory_set.push(new_area, None);
+            if area.map_type == MapType::Shared {
+                // both spaces now map the same frames
+                continue;
+            }
             for vpn in area.vpn_range {
                 let src_ppn = user_space.translate(vpn).unwrap().ppn();
                 let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
@@ -300,7 +455,7 @@ impl MemorySet {
                 va.into(),
                 (va.0 + PAGE_SIZE).into(),
                 MapType::Framed,
-                MapPermission::R | MapPermission::W | MapPermission::U,
+                self.stack_perm,
             ),
             None,
         );
@@ -314,6 +469,8 @@ pub struct MapArea {
     data_frames: BTreeMap<VirtPageNum, FrameTracker>,
     map_type: MapType,
     map_perm: MapPermission,
+    /// backing object of a `MapType::Shared` area
+    shm: Option<Arc<ShmObject>>,
 }
 
 impl MapArea {
@@ -330,6 +487,7 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            shm: None,
         }
     }
     pub fn from_another(another: &Self) -> Self {
@@ -338,6 +496,7 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            shm: another.shm.clone(),
         }
     }
     pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
@@ -351,6 +510,10 @@ impl MapArea {
                 ppn = frame.ppn;
                 self.data_frames.insert(vpn, frame);
             }
+            MapType::Shared => {
+                let page = vpn.0 - self.vpn_range.get_start().0;
+                ppn = self.shm.as_ref().unwrap().ppn(page);
+            }
         }
         let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
         page_table.map(vpn, ppn, pte_flags);
@@ -371,30 +534,145 @@ impl MapArea {
             self.unmap_one(page_table, vpn);
         }
     }
-    /// data: start-aligned but maybe with shorter length
+    /// data: placed `offset` bytes into the first page, maybe with shorter length
     /// assume that all frames were cleared before
-    pub fn copy_data(&mut self, page_table: &PageTable, data: &[u8]) {
+    pub fn copy_data(&mut self, page_table: &PageTable, offset: usize, data: &[u8]) {
         assert_eq!(self.map_type, MapType::Framed);
         let mut start: usize = 0;
+        let mut page_offset = offset;
         let mut current_vpn = self.vpn_range.get_start();
         let len = data.len();
-        loop {
-            let src = &data[start..len.min(start + PAGE_SIZE)];
+        while start < len {
+            let src = &data[start..len.min(start + PAGE_SIZE - page_offset)];
             let dst = &mut page_table
                 .translate(current_vpn)
                 .unwrap()
                 .ppn()
-                .get_bytes_array()[..src.len()];
+                .get_bytes_array()[page_offset..page_offset + src.len()];
             dst.copy_from_slice(src);
-            start += PAGE_SIZE;
-            if start >= len {
-                break;
-            }
+            start += src.len();
+            page_offset = 0;
             current_vpn.step();
         }
     }
 }
 
+const EM_RISCV: u16 = 243;
+const PT_GNU_STACK: u32 = 0x6474_e551;
+const PH_ENTRY_SIZE: usize = 56;
+const DT_NULL: u64 = 0;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+const RELA_ENTRY_SIZE: usize = 24;
+const R_RISCV_NONE: u32 = 0;
+const R_RISCV_RELATIVE: u32 = 3;
+
+/// why an image was refused by `MemorySet::from_elf`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ElfError {
+    /// rejected by the xmas_elf parser
+    Malformed(&'static str),
+    BadMagic,
+    NotElf64,
+    NotLittleEndian,
+    /// e_machine is not RISC-V
+    BadMachine(u16),
+    /// neither ET_EXEC nor ET_DYN
+    BadType,
+    BadProgramHeaders,
+    /// segment out of the file or out of user space
+    BadSegment,
+    BadAlignment,
+    SegmentOverlap,
+    NoLoadableSegment,
+    /// entry point is not inside an executable segment
+    BadEntry,
+    /// dynamically linked, we have no loader for PT_INTERP
+    Interpreter,
+    /// only R_RISCV_RELATIVE is supported for ET_DYN
+    UnsupportedRelocation(u32),
+}
+
+/// a PT_LOAD segment that passed validation
+struct ElfSegment {
+    start_va: VirtAddr,
+    end_va: VirtAddr,
+    start_vpn: VirtPageNum,
+    end_vpn: VirtPageNum,
+    map_perm: MapPermission,
+    /// unbiased p_vaddr
+    vaddr: usize,
+    offset: usize,
+    file_size: usize,
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Collect `(r_offset, r_addend)` of the RELA table named in PT_DYNAMIC,
+/// checking every target lies in a loaded segment.
+fn relative_relocations(
+    elf_data: &[u8],
+    dynamic: &[u8],
+    segments: &[ElfSegment],
+) -> Result<Vec<(usize, usize)>, ElfError> {
+    let (mut rela, mut rela_size, mut rela_ent) = (0, 0, RELA_ENTRY_SIZE);
+    for i in 0..dynamic.len() / 16 {
+        let (tag, val) = (read_u64(dynamic, i * 16), read_u64(dynamic, i * 16 + 8));
+        match tag {
+            DT_NULL => break,
+            DT_RELA => rela = val as usize,
+            DT_RELASZ => rela_size = val as usize,
+            DT_RELAENT => rela_ent = val as usize,
+            _ => {}
+        }
+    }
+    if rela_size == 0 {
+        return Ok(Vec::new());
+    }
+    if rela_ent != RELA_ENTRY_SIZE {
+        return Err(ElfError::Malformed("bad DT_RELAENT"));
+    }
+    // the table is addressed by vaddr, find it in the file through its segment
+    let table = segments
+        .iter()
+        .find(|seg| {
+            seg.vaddr <= rela
+                && rela
+                    .checked_add(rela_size)
+                    .is_some_and(|end| end <= seg.vaddr + seg.file_size)
+        })
+        .map(|seg| seg.offset + (rela - seg.vaddr))
+        .map(|start| &elf_data[start..start + rela_size])
+        .ok_or(ElfError::Malformed("DT_RELA outside of loaded segments"))?;
+    let mut relocations = Vec::new();
+    for entry in table.chunks_exact(RELA_ENTRY_SIZE) {
+        let r_offset = read_u64(entry, 0) as usize;
+        let r_type = read_u64(entry, 8) as u32;
+        let r_addend = read_u64(entry, 16) as usize;
+        match r_type {
+            R_RISCV_NONE => continue,
+            R_RISCV_RELATIVE => {}
+            _ => return Err(ElfError::UnsupportedRelocation(r_type)),
+        }
+        if !segments.iter().any(|seg| {
+            seg.map_perm.contains(MapPermission::W)
+                && seg.vaddr <= r_offset
+                && r_offset
+                    .checked_add(8)
+                    .is_some_and(|end| end <= seg.vaddr + (seg.end_va.0 - seg.start_va.0))
+        }) {
+            return Err(ElfError::Malformed("relocation outside of writable segments"));
+        }
+        relocations.push((r_offset, r_addend));
+    }
+    Ok(relocations)
+}
+
 use lazy_static::*;
 
 lazy_static! {
@@ -441,6 +719,8 @@ pub fn remap_test() {
 pub enum MapType {
     Identical,
     Framed,
+    /// frames owned by a `ShmObject`, shared with other spaces
+    Shared,
 }
 
 bitflags! {
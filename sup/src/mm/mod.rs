@@ -1,7 +1,8 @@
 pub use address::{PhysPageNum, VirtAddr, VirtPageNum, PhysAddr, StepByOne};
-pub use memory_set::{KERNEL_SPACE, MapPermission, MemorySet, remap_test, kernel_token};
+pub use memory_set::{KERNEL_SPACE, ElfError, MapPermission, MemorySet, remap_test, kernel_token};
 pub use page_table::*;
 pub use frame_allocator::{frame_alloc, frame_dealloc, FrameTracker};
+pub use shm::{ShmFile, ShmObject, shm_open};
 
 
 mod address;
@@ -11,9 +12,16 @@ mod frame_allocator;
 mod linked_list;
 mod memory_set;
 mod page_table;
+mod shm;
 
-pub fn init() {
+pub use allocator_test::{frame_allocator_test, heap_test};
+
+/// The heap comes first, the command line is parsed before the rest
+pub fn init_heap() {
     buddy_allocator::init_heap();
+}
+
+pub fn init() {
     // allocator_test::heap_test();
     frame_allocator::init_frame();
     // allocator_test::frame_allocator_test();
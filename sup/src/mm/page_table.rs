@@ -187,6 +187,30 @@ pub fn translated_str(token: usize, ptr: *const u8) -> String {
     string
 }
 
+/// Like `translated_str`, None if there is no NUL in the first `max` bytes
+pub fn translated_str_max(token: usize, ptr: *const u8, max: usize) -> Option<String> {
+    let mut page_table = PageTable::from_token(token);
+    let mut string = String::new();
+    let mut va = ptr as usize;
+    while string.len() < max {
+        let ch: u8 = *(page_table.translate_va(VirtAddr::from(va))?.get_mut());
+        if ch == 0 {
+            return Some(string);
+        }
+        string.push(ch as char);
+        va += 1;
+    }
+    None
+}
+
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+    let mut page_table = PageTable::from_token(token);
+    page_table
+        .translate_va(VirtAddr::from(ptr as usize))
+        .unwrap()
+        .get_ref()
+}
+
 pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
     //println!("into translated_refmut!");
     let mut page_table = PageTable::from_token(token);
@@ -0,0 +1,73 @@
+//! Named shared memory objects
+use super::address::PhysPageNum;
+use super::frame_allocator::{FrameTracker, frame_alloc};
+use crate::config::{PAGE_SIZE, SHM_MAX_SIZE};
+use crate::fs::File;
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// A named run of frames that several address spaces can map at once.
+/// Open fds and mappings hold it, the frames are freed with the last of them.
+pub struct ShmObject {
+    frames: Vec<FrameTracker>,
+}
+
+impl ShmObject {
+    pub fn pages(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn ppn(&self, page: usize) -> PhysPageNum {
+        self.frames[page].ppn
+    }
+}
+
+/// An fd of a shared memory object, only good for `sys_shm_map`
+pub struct ShmFile {
+    pub object: Arc<ShmObject>,
+}
+
+lazy_static! {
+    /// objects by name, an entry goes stale once nothing holds the object
+    static ref SHM_OBJECTS: UPSafeCell<BTreeMap<String, Weak<ShmObject>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Find the object called `name`, creating it with `size` bytes when missing.
+/// Opening an existing object with a larger size fails.
+pub fn shm_open(name: &str, size: usize) -> Option<Arc<ShmFile>> {
+    let mut objects = SHM_OBJECTS.exclusive_access();
+    objects.retain(|_, object| object.strong_count() > 0);
+    if let Some(object) = objects.get(name).and_then(Weak::upgrade) {
+        return (size <= object.pages() * PAGE_SIZE).then(|| Arc::new(ShmFile { object }));
+    }
+    if size == 0 || size > SHM_MAX_SIZE {
+        return None;
+    }
+    let mut frames = Vec::new();
+    for _ in 0..size.div_ceil(PAGE_SIZE) {
+        frames.push(frame_alloc()?);
+    }
+    let object = Arc::new(ShmObject { frames });
+    objects.insert(String::from(name), Arc::downgrade(&object));
+    Some(Arc::new(ShmFile { object }))
+}
+
+impl File for ShmFile {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: UserBuffer) -> isize {
+        -1
+    }
+    fn write(&self, _buf: UserBuffer) -> isize {
+        -1
+    }
+}
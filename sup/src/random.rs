@@ -0,0 +1,120 @@
+//! Kernel CSPRNG: ChaCha20 rekeyed from its own output after every request
+//! (fast key erasure), so what was handed out cannot be recovered from the
+//! state. The key is seeded at boot from the entropy devices and the clocks.
+use crate::drivers::read_entropy;
+use crate::drivers::rtc::rtc_time_ns;
+use crate::sync::UPSafeCell;
+use crate::timer::get_time;
+use lazy_static::*;
+use log::warn;
+
+/// flags of `sys_getrandom`
+pub const GRND_NONBLOCK: u32 = 1;
+pub const GRND_RANDOM: u32 = 2;
+pub const GRND_INSECURE: u32 = 4;
+
+/// "expand 32-byte k"
+const SIGMA: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+const BLOCK_SIZE: usize = 64;
+const KEY_SIZE: usize = 32;
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+/// Block `counter` of the key stream of `key`, with a nonce of zero
+fn chacha20_block(key: &[u32; 8], counter: u64) -> [u8; BLOCK_SIZE] {
+    let mut input = [0u32; 16];
+    input[..4].copy_from_slice(&SIGMA);
+    input[4..12].copy_from_slice(key);
+    input[12] = counter as u32;
+    input[13] = (counter >> 32) as u32;
+    let mut s = input;
+    for _ in 0..10 {
+        quarter_round(&mut s, 0, 4, 8, 12);
+        quarter_round(&mut s, 1, 5, 9, 13);
+        quarter_round(&mut s, 2, 6, 10, 14);
+        quarter_round(&mut s, 3, 7, 11, 15);
+        quarter_round(&mut s, 0, 5, 10, 15);
+        quarter_round(&mut s, 1, 6, 11, 12);
+        quarter_round(&mut s, 2, 7, 8, 13);
+        quarter_round(&mut s, 3, 4, 9, 14);
+    }
+    let mut block = [0u8; BLOCK_SIZE];
+    for ((bytes, word), start) in block.chunks_exact_mut(4).zip(s).zip(input) {
+        bytes.copy_from_slice(&word.wrapping_add(start).to_le_bytes());
+    }
+    block
+}
+
+struct ChaCha20Rng {
+    key: [u32; 8],
+}
+
+impl ChaCha20Rng {
+    /// Output comes from blocks 1 and on, block 0 is the next key
+    fn fill(&mut self, buf: &mut [u8]) {
+        for (counter, chunk) in (1..).zip(buf.chunks_mut(BLOCK_SIZE)) {
+            chunk.copy_from_slice(&chacha20_block(&self.key, counter)[..chunk.len()]);
+        }
+        self.rekey();
+    }
+    fn rekey(&mut self) {
+        let block = chacha20_block(&self.key, 0);
+        for (word, bytes) in self.key.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+    }
+    /// Fold `bytes` into the key, what was in it is kept
+    fn mix(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(KEY_SIZE) {
+            for (i, byte) in chunk.iter().enumerate() {
+                self.key[i / 4] ^= (*byte as u32) << (8 * (i % 4));
+            }
+            self.rekey();
+        }
+    }
+}
+
+lazy_static! {
+    static ref RNG: UPSafeCell<ChaCha20Rng> =
+        unsafe { UPSafeCell::new(ChaCha20Rng { key: [0; 8] }) };
+}
+
+/// Seed from the entropy devices, which must have been probed. Without one
+/// only the clocks go in, which is guessable.
+pub fn init() {
+    let mut seed = [0u8; KEY_SIZE];
+    let mut seeded = 0;
+    while seeded < KEY_SIZE {
+        let read = read_entropy(&mut seed[seeded..]);
+        if read == 0 {
+            break;
+        }
+        seeded += read;
+    }
+    if seeded < KEY_SIZE {
+        warn!("[kernel] no entropy device, random numbers are seeded from the clocks only");
+    }
+    let mut rng = RNG.exclusive_access();
+    rng.mix(&seed[..seeded]);
+    rng.mix(&rtc_time_ns().to_le_bytes());
+    rng.mix(&get_time().to_le_bytes());
+}
+
+/// Fill `buf` with random bytes, this never blocks
+pub fn fill_random(buf: &mut [u8]) {
+    RNG.exclusive_access().fill(buf);
+}
+
+/// Stir `bytes` into the state, like a write to /dev/random
+pub fn add_randomness(bytes: &[u8]) {
+    RNG.exclusive_access().mix(bytes);
+}
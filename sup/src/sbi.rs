@@ -1,26 +1,74 @@
+//! Calls into the SBI implementation below the kernel, the machine stage or
+//! OpenSBI/RustSBI. Output goes through the SBI console, input is read from
+//! the UART that interrupted, as SBI has no way to signal it.
 use log::{error, warn, info};
 
+use crate::board::board;
 use crate::uart::MmioSerialPort;
-use core::sync::atomic::{AtomicPtr, Ordering};
+use core::arch::asm;
 use core::fmt::{self, Write};
 
-static VIRT_UART0_BASE: usize = 0x10000000;
-static VIRT_TEST_BASE: usize = 0x100000;
+const LEGACY_CONSOLE_PUTCHAR: usize = 0x01;
+const EID_TIME: usize = 0x5449_4d45;
+const EID_SRST: usize = 0x5352_5354;
 
-static mut MSP: Option<MmioSerialPort> = None;
+const SRST_SHUTDOWN: usize = 0;
+const SRST_NO_REASON: usize = 0;
+const SRST_SYSTEM_FAILURE: usize = 1;
+
+/// Call `fid` of extension `eid`, gives the error and the value
+fn sbi_call(eid: usize, fid: usize, arg0: usize, arg1: usize) -> (isize, usize) {
+    let (error, value);
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") arg0 => error,
+            inlateout("a1") arg1 => value,
+            in("a6") fid,
+            in("a7") eid,
+        );
+    }
+    (error, value)
+}
+
+/// Legacy calls return in a0 alone
+fn sbi_legacy_call(eid: usize, arg0: usize) -> isize {
+    let ret;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") arg0 => ret,
+            in("a7") eid,
+        );
+    }
+    ret
+}
+
+/// Raise the supervisor timer interrupt once `time` reaches `stime_value`
+pub fn set_timer(stime_value: usize) {
+    sbi_call(EID_TIME, 0, stime_value, 0);
+}
+
+pub fn console_putchar(c: u8) {
+    sbi_legacy_call(LEGACY_CONSOLE_PUTCHAR, c as usize);
+}
+
+struct Console;
 
-impl fmt::Write for MmioSerialPort {
+impl fmt::Write for Console {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         for byte in s.bytes() {
-            self.send(byte);
+            console_putchar(byte);
         }
         Ok(())
     }
 }
 
+static mut MSP: Option<MmioSerialPort> = None;
+
 pub fn init_uart() {
     unsafe {
-        MSP = Some(MmioSerialPort::new(VIRT_UART0_BASE));
+        MSP = Some(MmioSerialPort::new(board().uart));
         if let Some(ref mut uart) = MSP {
             uart.init();
         }
@@ -28,19 +76,23 @@ pub fn init_uart() {
 }
 
 pub fn print(args: fmt::Arguments) {
-    unsafe {
-        if let Some(ref mut uart) = MSP {
-            uart.write_fmt(args).unwrap();
-        }
+    Console.write_fmt(args).unwrap();
+}
+
+/// Send bytes to the console as they are
+pub fn write_bytes(bytes: &[u8]) {
+    for &byte in bytes {
+        console_putchar(byte);
     }
 }
 
-pub fn scan() -> u8 {
+/// Take a received byte without waiting
+pub fn try_scan() -> Option<u8> {
     unsafe {
         if let Some(ref mut uart) = MSP {
-            uart.receive()
+            uart.try_receive()
         } else {
-            0
+            None
         }
     }
 }
@@ -60,28 +112,20 @@ macro_rules! println {
 }
 
 pub fn shutdown(failure: bool) -> ! {
-    let base_pointer = VIRT_TEST_BASE as *mut u32;
-    let shutdown_addr = AtomicPtr::new(base_pointer);
-    unsafe {
-        if failure {
-            error!("[kernel] Unspecified Error. POWEROFF");
-            shutdown_addr.load(Ordering::Relaxed).write(0x3333);
-        } else {
-            warn!("[kernel] POWEROFF");
-            shutdown_addr.load(Ordering::Relaxed).write(0x5555);
-        }
+    if failure {
+        error!("[kernel] Unspecified Error. POWEROFF");
+        sbi_call(EID_SRST, 0, SRST_SHUTDOWN, SRST_SYSTEM_FAILURE);
+    } else {
+        warn!("[kernel] POWEROFF");
+        sbi_call(EID_SRST, 0, SRST_SHUTDOWN, SRST_NO_REASON);
     }
     error!("Shutdown Failed");
     loop {}
 }
 
 pub fn reset() -> ! {
-    let base_pointer = VIRT_TEST_BASE as *mut u32;
-    let reset_addr = AtomicPtr::new(base_pointer);
-
-    unsafe {
-        info!("[kernel] RESET");
-        reset_addr.load(Ordering::Relaxed).write(0x7777);
-    }
+    info!("[kernel] RESET");
+    // type 1 is a cold reboot
+    sbi_call(EID_SRST, 0, 1, SRST_NO_REASON);
     panic!("Reset Failed");
 }
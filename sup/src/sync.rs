@@ -1,5 +1,8 @@
 //! Uniprocessor interior mutability primitives
 
+use crate::task::{TaskControlBlock, block_current_and_run_next, current_task, wakeup_task};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
 use core::cell::{RefCell, RefMut};
 
 pub struct UPSafeCell<T> {
@@ -22,3 +25,78 @@ impl<T> UPSafeCell<T> {
         self.inner.borrow_mut()
     }
 }
+
+/// Tasks blocked until some event, woken all at once by `wake_all`.
+/// Kept inside the `UPSafeCell` of the object they wait for.
+pub struct WaitQueue {
+    queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+    /// Register the current task; release the cell and call
+    /// `block_current_and_run_next` right after.
+    /// A task already waiting here is not added twice.
+    pub fn add_current(&mut self) {
+        let task = current_task().unwrap();
+        if !self.queue.iter().any(|waiting| Arc::ptr_eq(waiting, &task)) {
+            self.queue.push_back(task);
+        }
+    }
+    pub fn wake_all(&mut self) {
+        while let Some(task) = self.queue.pop_front() {
+            wakeup_task(task);
+        }
+    }
+}
+
+/// Lock whose waiters block instead of spinning, for sections that may sleep
+pub struct SleepLock {
+    inner: UPSafeCell<SleepLockInner>,
+}
+
+struct SleepLockInner {
+    locked: bool,
+    wait: WaitQueue,
+}
+
+pub struct SleepLockGuard<'a> {
+    lock: &'a SleepLock,
+}
+
+impl SleepLock {
+    pub const fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(SleepLockInner {
+                    locked: false,
+                    wait: WaitQueue::new(),
+                })
+            },
+        }
+    }
+    pub fn lock(&self) -> SleepLockGuard<'_> {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if !inner.locked {
+                inner.locked = true;
+                return SleepLockGuard { lock: self };
+            }
+            inner.wait.add_current();
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+}
+
+impl Drop for SleepLockGuard<'_> {
+    fn drop(&mut self) {
+        let mut inner = self.lock.inner.exclusive_access();
+        inner.locked = false;
+        inner.wait.wake_all();
+    }
+}
@@ -1,50 +1,109 @@
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_MKFIFO: usize = 33;
 const SYSCALL_MKDIR: usize = 34; // use mkdir to create dirs
 const SYSCALL_UNLINK: usize = 35; // use unlink to remove files/dirs
 const SYSCALL_RENAME: usize = 38; // use rename to rename files/dirs
+const SYSCALL_UMOUNT2: usize = 39; // no flags
+const SYSCALL_MOUNT: usize = 40; // no flags, `data` goes to the file system
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PPOLL: usize = 73; // no signals, the mask is ignored
 const SYSCALL_MV: usize = 82;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
 const SYSCALL_YIELD: usize = 124;
-const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_GETTIMEOFDAY: usize = 169;
+const SYSCALL_MQ_OPEN: usize = 180; // no unlink, a queue goes away with its last fd
+const SYSCALL_MQ_SEND: usize = 182;
+const SYSCALL_MQ_RECEIVE: usize = 183;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SHM_OPEN: usize = 194;
+const SYSCALL_SHM_MAP: usize = 196;
+const SYSCALL_SHM_UNMAP: usize = 197;
+const SYSCALL_SOCKET: usize = 198;
+const SYSCALL_SOCKETPAIR: usize = 199;
+const SYSCALL_BIND: usize = 200; // addresses are paths, like sun_path
+const SYSCALL_LISTEN: usize = 201;
+const SYSCALL_ACCEPT: usize = 202;
+const SYSCALL_CONNECT: usize = 203;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_GETRANDOM: usize = 278;
 
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+/// longest `#!` line looked at, like BINPRM_BUF_SIZE
+const SHEBANG_LINE_MAX: usize = 127;
+
+pub fn syscall(syscall_id: usize, args: [usize; 5]) -> isize {
     match syscall_id {
+        SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        SYSCALL_MKFIFO => sys_mkfifo(args[0] as *const u8),
         SYSCALL_MKDIR => sys_mkdir(args[0] as *const u8),
         SYSCALL_UNLINK => sys_remove(args[0] as *const u8),
         SYSCALL_RENAME => sys_rename(args[0] as *const u8, args[1] as *const u8),
+        SYSCALL_UMOUNT2 => sys_umount2(args[0] as *const u8, args[1]),
+        SYSCALL_MOUNT => sys_mount(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *const u8,
+            args[3],
+            args[4] as *const u8,
+        ),
         SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
         SYSCALL_CLOSE => sys_close(args[0]),
-        SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
+        SYSCALL_PIPE => sys_pipe(args[0] as *mut usize, args[1] as u32),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_PPOLL => sys_ppoll(args[0] as *mut PollFd, args[1], args[2] as *const TimeSpec),
         SYSCALL_MV => sys_mv(args[0] as *const u8, args[1] as *const u8),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut TimeSpec),
         SYSCALL_YIELD => sys_yield(),
-        SYSCALL_GET_TIME => sys_get_time(),
+        SYSCALL_KILL => sys_kill(args[0], args[1]),
+        SYSCALL_GETTIMEOFDAY => sys_gettimeofday(args[0] as *mut TimeVal),
+        SYSCALL_MQ_OPEN => sys_mq_open(args[0] as *const u8, args[1] as u32, args[2] as *const MqAttr),
+        SYSCALL_MQ_SEND => sys_mq_send(args[0], args[1] as *const u8, args[2], args[3] as u32),
+        SYSCALL_MQ_RECEIVE => sys_mq_receive(args[0], args[1] as *const u8, args[2], args[3] as *mut u32),
         SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_SHM_OPEN => sys_shm_open(args[0] as *const u8, args[1]),
+        SYSCALL_SHM_MAP => sys_shm_map(args[0]),
+        SYSCALL_SHM_UNMAP => sys_shm_unmap(args[0]),
+        SYSCALL_SOCKET => sys_socket(args[0], args[1], args[2]),
+        SYSCALL_SOCKETPAIR => sys_socketpair(args[0], args[1], args[2], args[3] as *mut usize),
+        SYSCALL_BIND => sys_bind(args[0], args[1] as *const u8),
+        SYSCALL_LISTEN => sys_listen(args[0], args[1]),
+        SYSCALL_ACCEPT => sys_accept(args[0]),
+        SYSCALL_CONNECT => sys_connect(args[0], args[1] as *const u8),
         SYSCALL_FORK => sys_fork(),
-        SYSCALL_EXEC => sys_exec(args[0] as *const u8),
+        SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
         SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_GETRANDOM => sys_getrandom(args[0] as *const u8, args[1], args[2] as u32),
         _ => panic!("Unsupported syscall_id: {}", syscall_id),
     }
 }
 
+use alloc::format;
+use core::any::Any;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 use crate::alloc::string::ToString;
-use crate::fs::{OpenFlags, mkdir_at_root, open_bin, open_file, remove_at_root, rename_at_root, make_pipe, move_at_root};
-use crate::mm::{UserBuffer, translated_byte_buffer, translated_refmut, translated_str};
-use crate::sbi::scan;
+use crate::config::{ARG_MAX, MICRO_PER_SEC, MSEC_PER_SEC, NSEC_PER_SEC, SHEBANG_MAX_DEPTH};
+use crate::fs::{AF_UNIX, EINTR, EINVAL, File, MqAttr, MqFile, OpenFlags, PollEvents, PollFd, SOCK_DGRAM, SOCK_STREAM, Socket, SocketType, mount, open_mqueue, umount, mkdir_at_root, mkfifo_at_root, open_exec, open_file, remove_at_root, rename_at_root, make_pipe, move_at_root};
+use crate::mm::{ShmFile, UserBuffer, VirtAddr, shm_open, translated_byte_buffer, translated_ref, translated_refmut, translated_str, translated_str_max};
 use crate::task::*;
-use crate::timer::get_time_ms;
+use crate::drivers::rtc::rtc_time_ns;
+use crate::random::{GRND_INSECURE, GRND_NONBLOCK, GRND_RANDOM, fill_random};
+use crate::timer::{CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME, TimeSpec, TimeVal, add_timer, get_time_ns, ticks_to_ns};
 use crate::{print, println};
+use log::warn;
 
 const FD_STDIN: usize = 0;
 const FD_STDOUT: usize = 1;
@@ -63,7 +122,7 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
         let file = file.clone();
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len)))
     } else {
         -1
     }
@@ -83,7 +142,7 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
         }
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len)))
     } else {
         -1
     }
@@ -93,13 +152,14 @@ pub fn sys_open(path: *const u8, flags: u32) -> isize {
     let task = current_task().unwrap();
     let token = current_user_token();
     let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = task.inner_exclusive_access();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
+    match open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
+        Ok(inode) => {
+            let mut inner = task.inner_exclusive_access();
+            let fd = inner.alloc_fd();
+            inner.fd_table[fd] = Some(inode);
+            fd as isize
+        }
+        Err(errno) => errno,
     }
 }
 
@@ -116,6 +176,18 @@ pub fn sys_close(fd: usize) -> isize {
     0
 }
 
+/// Copy `fd` to the lowest free fd
+pub fn sys_dup(fd: usize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let Some(Some(file)) = inner.fd_table.get(fd).cloned() else {
+        return -1;
+    };
+    let new_fd = inner.alloc_fd();
+    inner.fd_table[new_fd] = Some(file);
+    new_fd as isize
+}
+
 pub fn sys_mkdir(path: *const u8) -> isize {
     let token = current_user_token();
     let path = translated_str(token, path);
@@ -126,6 +198,12 @@ pub fn sys_mkdir(path: *const u8) -> isize {
     }
 }
 
+pub fn sys_mkfifo(path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    if mkfifo_at_root(path.as_str()) { 0 } else { -1 }
+}
+
 pub fn sys_remove(path: *const u8) -> isize {
     let token = current_user_token();
     let path = translated_str(token, path);
@@ -146,11 +224,44 @@ pub fn sys_rename(path: *const u8, new_name: *const u8) -> isize {
     if rename_at_root(path.as_str(), new_name.as_str()) { 0 } else { -1 }
 }
 
-pub fn sys_pipe(pipe: *mut usize) -> isize {
+/// Mount a file system of type `fs_type` from `source` on `target`,
+/// `data` may be null
+pub fn sys_mount(
+    source: *const u8,
+    target: *const u8,
+    fs_type: *const u8,
+    flags: usize,
+    data: *const u8,
+) -> isize {
+    if flags != 0 {
+        return EINVAL;
+    }
+    let token = current_user_token();
+    let data = if data.is_null() { String::new() } else { translated_str(token, data) };
+    mount(
+        &translated_str(token, source),
+        &translated_str(token, target),
+        &translated_str(token, fs_type),
+        &data,
+    )
+}
+
+pub fn sys_umount2(target: *const u8, flags: usize) -> isize {
+    if flags != 0 {
+        return EINVAL;
+    }
+    umount(&translated_str(current_user_token(), target))
+}
+
+/// `flags` may hold `OpenFlags::NONBLOCK`, like pipe2
+pub fn sys_pipe(pipe: *mut usize, flags: u32) -> isize {
+    let Some(flags) = OpenFlags::from_bits(flags) else {
+        return -1;
+    };
     let task = current_task().unwrap();
     let token = current_user_token();
     let mut inner = task.inner_exclusive_access();
-    let (pipe_read, pipe_write) = make_pipe();
+    let (pipe_read, pipe_write) = make_pipe(flags.contains(OpenFlags::NONBLOCK));
     let read_fd = inner.alloc_fd();
     inner.fd_table[read_fd] = Some(pipe_read);
     let write_fd = inner.alloc_fd();
@@ -160,6 +271,73 @@ pub fn sys_pipe(pipe: *mut usize) -> isize {
     0
 }
 
+/// Wait until one of `nfds` files in `fds` is ready or `timeout` passes,
+/// a null `timeout` waits forever. Return the number of ready entries.
+pub fn sys_ppoll(fds: *mut PollFd, nfds: usize, timeout: *const TimeSpec) -> isize {
+    let token = current_user_token();
+    let deadline = (!timeout.is_null())
+        .then(|| get_time_ns().saturating_add(translated_ref(token, timeout).as_ns()));
+    // one timer for the whole wait, rounded up to a whole ms so it is not early
+    if let Some(deadline) = deadline {
+        add_timer(deadline.div_ceil(NSEC_PER_SEC / MSEC_PER_SEC), current_task().unwrap());
+    }
+    loop {
+        let task = current_task().unwrap();
+        let inner = task.inner_exclusive_access();
+        let files: Vec<Option<Arc<dyn File + Send + Sync>>> = (0..nfds)
+            .map(|i| {
+                let fd = translated_ref(token, unsafe { fds.add(i) }).fd;
+                usize::try_from(fd)
+                    .ok()
+                    .and_then(|fd| inner.fd_table.get(fd).cloned().flatten())
+            })
+            .collect();
+        drop(inner);
+        let mut ready = 0;
+        for (i, file) in files.iter().enumerate() {
+            let pollfd = translated_refmut(token, unsafe { fds.add(i) });
+            let revents = match file {
+                Some(file) => {
+                    // errors and hangups are reported even when not asked for
+                    let wanted = PollEvents::from_bits_truncate(pollfd.events)
+                        | PollEvents::ERR
+                        | PollEvents::HUP;
+                    file.poll() & wanted
+                }
+                None if pollfd.fd < 0 => PollEvents::empty(),
+                None => PollEvents::NVAL,
+            };
+            pollfd.revents = revents.bits();
+            if !revents.is_empty() {
+                ready += 1;
+            }
+        }
+        if ready > 0 || deadline.is_some_and(|deadline| get_time_ns() >= deadline) {
+            return ready;
+        }
+        if current_signal_pending() {
+            return EINTR;
+        }
+        for file in files.iter().flatten() {
+            file.add_poll_waiter();
+        }
+        drop(files);
+        drop(task);
+        block_current_and_run_next();
+    }
+}
+
+/// Device specific request on `fd`, `arg` is usually a pointer
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let Some(Some(file)) = inner.fd_table.get(fd).cloned() else {
+        return -1;
+    };
+    drop(inner);
+    file.ioctl(request, arg)
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
@@ -170,8 +348,55 @@ pub fn sys_yield() -> isize {
     0
 }
 
-pub fn sys_get_time() -> isize {
-    get_time_ms() as isize
+/// Send signal `signum` to process `pid`, signal 0 only checks that it exists.
+/// A signal that would end or stop initproc fails with -1, as EPERM.
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    if signum == 0 {
+        return if pid2task(pid).is_some() { 0 } else { -1 };
+    }
+    match SignalFlags::from_signum(signum) {
+        Some(signal) if send_signal(pid, signal) => 0,
+        _ => -1,
+    }
+}
+
+/// Wall-clock time into `tv` unless it is null. The time zone argument is
+/// ignored, as on Linux.
+pub fn sys_gettimeofday(tv: *mut TimeVal) -> isize {
+    if tv.is_null() {
+        return 0;
+    }
+    let ns = rtc_time_ns();
+    *translated_refmut(current_user_token(), tv) = TimeVal {
+        sec: ns / NSEC_PER_SEC,
+        usec: ns % NSEC_PER_SEC / (NSEC_PER_SEC / MICRO_PER_SEC),
+    };
+    0
+}
+
+/// Time of `clock_id` into `tp`: the wall clock, time since boot or the time
+/// the calling process has run
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> isize {
+    let ns = match clock_id {
+        CLOCK_REALTIME => rtc_time_ns(),
+        CLOCK_MONOTONIC => get_time_ns(),
+        CLOCK_PROCESS_CPUTIME_ID => ticks_to_ns(current_cpu_time()),
+        _ => return EINVAL,
+    };
+    *translated_refmut(current_user_token(), tp) = TimeSpec::from_ns(ns);
+    0
+}
+
+/// `len` random bytes into `buf`. The pool is seeded at boot, so no flag
+/// changes anything, unknown ones are refused.
+pub fn sys_getrandom(buf: *const u8, len: usize, flags: u32) -> isize {
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0 {
+        return EINVAL;
+    }
+    for buffer in translated_byte_buffer(current_user_token(), buf, len) {
+        fill_random(buffer);
+    }
+    len as isize
 }
 
 pub fn sys_getpid() -> isize {
@@ -187,21 +412,229 @@ pub fn sys_fork() -> isize {
     // we do not have to move to next instruction since we have done it before
     // for child process, fork returns 0
     trap_cx.x[10] = 0;
+    insert_into_pid2task(new_pid, new_task.clone());
     // add new task to scheduler
     add_task(new_task);
     new_pid as isize
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
+/// `args` is a null terminated array of string pointers, or null for `[path]`.
+/// Files starting with `#!` are run by the named interpreter.
+pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    let mut path = translated_str(token, path);
+    let mut args_vec: Vec<String> = Vec::new();
+    if !args.is_null() {
+        // counted as they come, so a huge argv is not copied in first
+        let mut args_size = 0;
+        loop {
+            let arg_str_ptr = *translated_ref(token, args);
+            if arg_str_ptr == 0 {
+                break;
+            }
+            let Some(arg) =
+                translated_str_max(token, arg_str_ptr as *const u8, ARG_MAX - args_size)
+            else {
+                return -1;
+            };
+            args_size += arg.len() + 1 + size_of::<usize>();
+            if args_size > ARG_MAX {
+                return -1;
+            }
+            args_vec.push(arg);
+            unsafe {
+                args = args.add(1);
+            }
+        }
+    }
+    if args_vec.is_empty() {
+        args_vec.push(path.clone());
+    }
     // println!("\nbefore open path {}",path);
-    if let Some(app_inode) = open_bin(path.as_str()) {
+    for _ in 0..=SHEBANG_MAX_DEPTH {
+        let Some(app_inode) = open_exec(path.as_str()) else {
+            return -1;
+        };
         // println!("\npath {}",path);
         let all_data = app_inode.read_all();
         // println!("\nafter readall {}",path);
+        if all_data.starts_with(b"#!") {
+            let Some((interpreter, optional_arg)) = parse_shebang(&all_data) else {
+                warn!("[kernel] exec {}: bad #! line", path);
+                return -1;
+            };
+            // argv becomes [interpreter, optional arg, script, original argv[1..]]
+            let script = if path.contains('/') {
+                path.trim_start_matches('/').to_string()
+            } else {
+                format!("bin/{}", path)
+            };
+            let mut new_args = vec![interpreter.clone()];
+            new_args.extend(optional_arg);
+            new_args.push(script);
+            new_args.extend(args_vec.drain(..).skip(1));
+            args_vec = new_args;
+            path = interpreter;
+            continue;
+        }
+        let args_size: usize = args_vec
+            .iter()
+            .map(|arg| arg.len() + 1 + size_of::<usize>())
+            .sum();
+        if args_size > ARG_MAX {
+            return -1;
+        }
         let task = current_task().unwrap();
-        task.exec(all_data.as_slice());
+        return match task.exec(all_data.as_slice(), args_vec) {
+            Ok(()) => 0,
+            Err(err) => {
+                warn!("[kernel] exec {}: {:?}", path, err);
+                -1
+            }
+        };
+    }
+    warn!("[kernel] exec {}: too many levels of #!", path);
+    -1
+}
+
+/// Split the `#!` line into the interpreter and its single optional argument.
+fn parse_shebang(data: &[u8]) -> Option<(String, Option<String>)> {
+    let line = &data[2..];
+    let end = line
+        .iter()
+        .position(|c| *c == b'\n')
+        .unwrap_or(line.len())
+        .min(SHEBANG_LINE_MAX);
+    let line = core::str::from_utf8(&line[..end]).ok()?.trim();
+    let (interpreter, arg) = match line.split_once([' ', '\t']) {
+        Some((interpreter, arg)) => (interpreter, Some(arg.trim())),
+        None => (line, None),
+    };
+    if interpreter.is_empty() {
+        return None;
+    }
+    Some((
+        interpreter.to_string(),
+        arg.filter(|arg| !arg.is_empty()).map(|arg| arg.to_string()),
+    ))
+}
+
+/// Open the message queue `name`, `attr` may be null for default limits.
+pub fn sys_mq_open(name: *const u8, flags: u32, attr: *const MqAttr) -> isize {
+    let token = current_user_token();
+    let name = translated_str(token, name);
+    let Some(flags) = OpenFlags::from_bits(flags) else {
+        return -1;
+    };
+    let attr = if attr.is_null() {
+        MqAttr::default()
+    } else {
+        *translated_ref(token, attr)
+    };
+    let (readable, writable) = flags.read_write();
+    if let Some(mq) = open_mqueue(
+        name.as_str(),
+        readable,
+        writable,
+        flags.contains(OpenFlags::CREATE),
+        attr,
+    ) {
+        let task = current_task().unwrap();
+        let mut inner = task.inner_exclusive_access();
+        let fd = inner.alloc_fd();
+        inner.fd_table[fd] = Some(mq);
+        fd as isize
+    } else {
+        -1
+    }
+}
+
+/// Get the file at `fd` if it is a message queue
+fn mqueue_file(fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let file = inner.fd_table.get(fd)?.clone()?;
+    (file.as_ref() as &dyn Any).is::<MqFile>().then_some(file)
+}
+
+pub fn sys_mq_send(fd: usize, buf: *const u8, len: usize, prio: u32) -> isize {
+    let token = current_user_token();
+    let Some(file) = mqueue_file(fd) else {
+        return -1;
+    };
+    if !file.writable() {
+        return -1;
+    }
+    let mq = (file.as_ref() as &dyn Any).downcast_ref::<MqFile>().unwrap();
+    mq.send(UserBuffer::new(translated_byte_buffer(token, buf, len)), prio)
+}
+
+/// Return the length of the received message and store its priority to `prio`
+/// unless it is null.
+pub fn sys_mq_receive(fd: usize, buf: *const u8, len: usize, prio: *mut u32) -> isize {
+    let token = current_user_token();
+    let Some(file) = mqueue_file(fd) else {
+        return -1;
+    };
+    if !file.readable() {
+        return -1;
+    }
+    let mq = (file.as_ref() as &dyn Any).downcast_ref::<MqFile>().unwrap();
+    match mq.receive(UserBuffer::new(translated_byte_buffer(token, buf, len))) {
+        Ok((msg_len, msg_prio)) => {
+            if !prio.is_null() {
+                *translated_refmut(token, prio) = msg_prio;
+            }
+            msg_len as isize
+        }
+        Err(errno) => errno,
+    }
+}
+
+/// Open the shared memory object `name`, creating it with `size` bytes if missing.
+/// Return an fd of it, or -1 if it exists but is smaller than `size`. The
+/// object is gone once its fds are closed and its mappings unmapped.
+pub fn sys_shm_open(name: *const u8, size: usize) -> isize {
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let name = translated_str(token, name);
+    match shm_open(name.as_str(), size) {
+        Some(shm) => {
+            let mut inner = task.inner_exclusive_access();
+            let fd = inner.alloc_fd();
+            inner.fd_table[fd] = Some(shm);
+            fd as isize
+        }
+        None => -1,
+    }
+}
+
+/// Map the shared memory object open at `fd` into the current process, return its address.
+pub fn sys_shm_map(fd: usize) -> isize {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let Some(Some(file)) = inner.fd_table.get(fd) else {
+        return -1;
+    };
+    let Some(shm) = (file.as_ref() as &dyn Any).downcast_ref::<ShmFile>() else {
+        return -1;
+    };
+    let object = shm.object.clone();
+    match inner.memory_set.map_shared(object) {
+        Some(addr) => addr as isize,
+        None => -1,
+    }
+}
+
+/// Unmap the shared memory mapped at `addr` by `sys_shm_map`.
+pub fn sys_shm_unmap(addr: usize) -> isize {
+    let addr = VirtAddr::from(addr);
+    if !addr.aligned() {
+        return -1;
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.memory_set.unmap_shared(addr.floor()) {
         0
     } else {
         -1
@@ -244,3 +677,97 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
     }
     // ---- release current PCB lock automatically
 }
+
+/// Split a socket type into its kind and whether it is non-blocking
+fn socket_type(type_: usize) -> Option<(SocketType, bool)> {
+    let nonblock = type_ & OpenFlags::NONBLOCK.bits() as usize != 0;
+    match type_ & !(OpenFlags::NONBLOCK.bits() as usize) {
+        SOCK_STREAM => Some((SocketType::Stream, nonblock)),
+        SOCK_DGRAM => Some((SocketType::Datagram, nonblock)),
+        _ => None,
+    }
+}
+
+/// Get the file at `fd` if it is a socket
+fn socket_file(fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let file = inner.fd_table.get(fd)?.clone()?;
+    (file.as_ref() as &dyn Any).is::<Socket>().then_some(file)
+}
+
+pub fn sys_socket(domain: usize, type_: usize, _protocol: usize) -> isize {
+    let Some((type_, nonblock)) = socket_type(type_).filter(|_| domain == AF_UNIX) else {
+        return -1;
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd();
+    inner.fd_table[fd] = Some(Socket::new(type_, nonblock));
+    fd as isize
+}
+
+pub fn sys_socketpair(domain: usize, type_: usize, _protocol: usize, sv: *mut usize) -> isize {
+    let Some((type_, nonblock)) = socket_type(type_).filter(|_| domain == AF_UNIX) else {
+        return -1;
+    };
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let mut inner = task.inner_exclusive_access();
+    let (a, b) = Socket::pair(type_, nonblock);
+    let fd_a = inner.alloc_fd();
+    inner.fd_table[fd_a] = Some(a);
+    let fd_b = inner.alloc_fd();
+    inner.fd_table[fd_b] = Some(b);
+    *translated_refmut(token, sv) = fd_a;
+    *translated_refmut(token, unsafe { sv.add(1) }) = fd_b;
+    0
+}
+
+/// Bind the socket to a new socket file at `path`
+pub fn sys_bind(fd: usize, path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let Some(file) = socket_file(fd) else {
+        return -1;
+    };
+    let socket = (file as Arc<dyn Any + Send + Sync>).downcast::<Socket>().unwrap();
+    if socket.bind(path.as_str()) { 0 } else { -1 }
+}
+
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    let Some(file) = socket_file(fd) else {
+        return -1;
+    };
+    let socket = (file.as_ref() as &dyn Any).downcast_ref::<Socket>().unwrap();
+    if socket.listen(backlog) { 0 } else { -1 }
+}
+
+/// Wait for a connection on a listening socket, return the fd of its server end
+pub fn sys_accept(fd: usize) -> isize {
+    let Some(file) = socket_file(fd) else {
+        return -1;
+    };
+    let socket = (file.as_ref() as &dyn Any).downcast_ref::<Socket>().unwrap();
+    match socket.accept() {
+        Ok(connection) => {
+            let task = current_task().unwrap();
+            let mut inner = task.inner_exclusive_access();
+            let fd = inner.alloc_fd();
+            inner.fd_table[fd] = Some(connection);
+            fd as isize
+        }
+        Err(errno) => errno,
+    }
+}
+
+/// Connect to the socket bound at `path`
+pub fn sys_connect(fd: usize, path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let Some(file) = socket_file(fd) else {
+        return -1;
+    };
+    let socket = (file.as_ref() as &dyn Any).downcast_ref::<Socket>().unwrap();
+    socket.connect(path.as_str())
+}
@@ -1,4 +1,4 @@
-use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::{collections::{BTreeMap, vec_deque::VecDeque}, sync::Arc};
 
 use crate::sync::UPSafeCell;
 
@@ -27,6 +27,22 @@ lazy_static! {
     pub static ref TASK_MANAGER: UPSafeCell<TaskManager> = unsafe {
         UPSafeCell::new(TaskManager::new())
     };
+    /// live processes by pid, to reach them with signals
+    pub static ref PID2TCB: UPSafeCell<BTreeMap<usize, Arc<TaskControlBlock>>> = unsafe {
+        UPSafeCell::new(BTreeMap::new())
+    };
+}
+
+pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    PID2TCB.exclusive_access().get(&pid).cloned()
+}
+
+pub fn insert_into_pid2task(pid: usize, task: Arc<TaskControlBlock>) {
+    PID2TCB.exclusive_access().insert(pid, task);
+}
+
+pub fn remove_from_pid2task(pid: usize) {
+    PID2TCB.exclusive_access().remove(&pid);
 }
 
 pub fn add_task(task: Arc<TaskControlBlock>) {
@@ -5,26 +5,32 @@ mod task;
 mod pid;
 mod processor;
 mod manager;
+mod signal;
 
 use crate::println;
 use alloc::sync::Arc;
 use lazy_static::*;
-use task::{TaskControlBlock, TaskStatus};
+pub use task::TaskControlBlock;
+use task::TaskStatus;
 pub use processor::*;
 pub use manager::*;
-use crate::fs::{open_file, open_bin, OpenFlags};
+use crate::cmdline::kernel_config;
+use crate::fs::{open_file, open_exec, OpenFlags};
 
 pub use context::TaskContext;
+pub use signal::SignalFlags;
 
 lazy_static! {
     pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
-        let inode = open_bin("initproc").unwrap();
+        let init = kernel_config().init;
+        let inode = open_exec(&init).unwrap_or_else(|| panic!("No init program {}", init));
         let v = inode.read_all();
         TaskControlBlock::new(v.as_slice())
     });
 }
 
 pub fn add_initproc() {
+    insert_into_pid2task(INITPROC.getpid(), INITPROC.clone());
     add_task(INITPROC.clone());
 }
 
@@ -39,6 +45,27 @@ pub fn suspend_current_and_run_next(){
     schedule(task_cx_ptr);
 }
 
+/// Leave the CPU without joining the ready queue, the task runs again only
+/// after `wakeup_task`
+pub fn block_current_and_run_next() {
+    let task = take_current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut inner.task_cx as *mut TaskContext;
+    inner.task_status = TaskStatus::Blocked;
+    drop(inner);
+    schedule(task_cx_ptr);
+}
+
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    let mut inner = task.inner_exclusive_access();
+    if inner.task_status != TaskStatus::Blocked {
+        return;
+    }
+    inner.task_status = TaskStatus::Ready;
+    drop(inner);
+    add_task(task);
+}
+
 pub const IDLE_PID: usize = 0;
 
 pub fn exit_current_and_run_next(exit_code: i32){
@@ -48,6 +75,7 @@ pub fn exit_current_and_run_next(exit_code: i32){
     let mut inner = task.inner_exclusive_access();
     // Change status to Zombie
     inner.task_status = TaskStatus::Zombie;
+    remove_from_pid2task(task.getpid());
     // Record exit code
     inner.exit_code = exit_code;
     // do not move to its parent but under initproc
@@ -65,9 +93,63 @@ pub fn exit_current_and_run_next(exit_code: i32){
     inner.children.clear();
     // deallocate user space
     inner.memory_set.recycle_data_pages();
+    // close files now, pipe peers must not wait for our parent to reap us
+    let fd_table = core::mem::take(&mut inner.fd_table);
     drop(inner);
+    drop(fd_table);
     // drop task manually to maintain rc correctly
     drop(task);
     let mut _unused = TaskContext::zero_init();
     schedule(&mut _unused as *mut _);
 }
+
+/// Post `signal` to process `pid`, a blocked process is woken to notice it.
+/// Refused for initproc when it would end or stop it, orphans need a reaper.
+pub fn send_signal(pid: usize, signal: SignalFlags) -> bool {
+    let Some(task) = pid2task(pid) else {
+        return false;
+    };
+    if Arc::ptr_eq(&task, &INITPROC) && signal.intersects(SignalFlags::fatal() | SignalFlags::stop()) {
+        return false;
+    }
+    task.inner_exclusive_access().signals |= signal;
+    wakeup_task(task);
+    true
+}
+
+/// Whether the current process should leave a blocking wait for a signal
+pub fn current_signal_pending() -> bool {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    inner
+        .signals
+        .intersects(SignalFlags::fatal() | SignalFlags::stop())
+}
+
+/// Take the default action of pending signals before going back to user mode
+pub fn handle_signals() {
+    loop {
+        let task = current_task().unwrap();
+        let mut inner = task.inner_exclusive_access();
+        let fatal = inner.signals & SignalFlags::fatal();
+        if !fatal.is_empty() {
+            drop(inner);
+            drop(task);
+            exit_current_and_run_next(-(fatal.signum() as i32));
+            return;
+        }
+        if inner.signals.intersects(SignalFlags::stop()) {
+            inner.frozen = true;
+        }
+        if inner.signals.contains(SignalFlags::SIGCONT) {
+            inner.frozen = false;
+        }
+        inner.signals.remove(SignalFlags::stop() | SignalFlags::SIGCONT);
+        if !inner.frozen {
+            return;
+        }
+        drop(inner);
+        drop(task);
+        block_current_and_run_next();
+    }
+}
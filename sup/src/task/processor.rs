@@ -1,12 +1,22 @@
 use alloc::sync::Arc;
 
-use crate::{mm::VirtAddr, sync::UPSafeCell, trap::TrapContext};
+use crate::{
+    drivers::irq_handler,
+    mm::VirtAddr,
+    sync::UPSafeCell,
+    timer::{check_timer, get_time, set_idle_trigger, set_next_trigger},
+    trap::TrapContext,
+};
+use core::arch::asm;
+use riscv::register::sip;
 
 use super::{manager::fetch_task, switch::__switch, task::{TaskControlBlock, TaskStatus}, TaskContext};
 
 pub struct Processor {
     current: Option<Arc<TaskControlBlock>>,
     idle_task_cx: TaskContext,
+    /// when `current` was switched to
+    running_since: usize,
 }
 
 impl Processor {
@@ -14,6 +24,7 @@ impl Processor {
         Self {
             current: None,
             idle_task_cx: TaskContext::zero_init(),
+            running_since: 0,
         }
     }
     fn get_idle_task_cx_ptr(&mut self) -> *mut TaskContext {
@@ -42,6 +53,14 @@ pub fn current_task() -> Option<Arc<TaskControlBlock>> {
     PROCESSOR.exclusive_access().current()
 }
 
+/// Ticks the current task has run, this turn included
+pub fn current_cpu_time() -> usize {
+    let running_since = PROCESSOR.exclusive_access().running_since;
+    let task = current_task().unwrap();
+    let cpu_time = task.inner_exclusive_access().cpu_time;
+    cpu_time + get_time() - running_since
+}
+
 pub fn current_user_token() -> usize {
     let task = current_task().unwrap();
     let token = task.inner_exclusive_access().get_user_token();
@@ -80,9 +99,14 @@ pub fn run_tasks() {
             task_inner.task_status = TaskStatus::Running;
             // stop exclusively accessing coming task TCB manually
             drop(task_inner);
+            // kept to charge it the time it runs, even if it exits
+            let running = task.clone();
             processor.current = Some(task);
+            processor.running_since = get_time();
             // stop exclusively accessing processor manually
             drop(processor);
+            // a fresh time slice
+            set_next_trigger();
             unsafe {
                 __switch(
                     idle_task_cx_ptr,
@@ -91,10 +115,31 @@ pub fn run_tasks() {
                 // when first called,
                 // __switch can store the context of the kernal startup
             }
+            let ran = get_time() - PROCESSOR.exclusive_access().running_since;
+            running.inner_exclusive_access().cpu_time += ran;
+        } else {
+            // interrupts are off in the kernel, so sleepers are woken from here
+            drop(processor);
+            let woken = check_timer();
+            if sip::read().sext() {
+                irq_handler();
+            } else if !woken {
+                wait_for_interrupt();
+            }
         }
     }
 }
 
+/// Stop the hart until the first sleeper is due or a device interrupts.
+/// `wfi` also wakes for interrupts `sie` enables while `sstatus.SIE` is clear,
+/// so nothing is taken in the kernel and nothing pending is slept through.
+fn wait_for_interrupt() {
+    set_idle_trigger();
+    unsafe {
+        asm!("wfi");
+    }
+}
+
 // when a process has run out its time
 // to idle control flow
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
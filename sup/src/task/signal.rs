@@ -0,0 +1,36 @@
+//! Pending signals of a process, only default actions are supported
+use bitflags::*;
+
+bitflags! {
+    /// Bit `1 << n` is signal number `n`, as on Linux
+    #[derive(Clone, Copy, PartialEq)]
+    pub struct SignalFlags: u32 {
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGKILL = 1 << 9;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+    }
+}
+
+impl SignalFlags {
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        if signum == 0 || signum >= 32 {
+            return None;
+        }
+        Self::from_bits(1 << signum)
+    }
+    /// Signals whose default action ends the process
+    pub fn fatal() -> Self {
+        Self::SIGINT | Self::SIGQUIT | Self::SIGKILL
+    }
+    /// Signals whose default action stops the process
+    pub fn stop() -> Self {
+        Self::SIGSTOP | Self::SIGTSTP
+    }
+    /// Number of the lowest signal set
+    pub fn signum(&self) -> usize {
+        self.bits().trailing_zeros() as usize
+    }
+}
@@ -4,11 +4,13 @@ use core::fmt::Debug;
 
 use super::TaskContext;
 use super::pid::{KernelStack, PidHandle, pid_alloc};
+use super::signal::SignalFlags;
 use crate::config::{kernel_stack_position, PAGE_SIZE, TRAP_CONTEXT, USER_STACK_SIZE};
-use crate::mm::{KERNEL_SPACE, MapPermission, MemorySet, PhysPageNum, VirtAddr, VirtPageNum};
+use crate::mm::{ElfError, KERNEL_SPACE, MapPermission, MemorySet, translated_refmut, PhysPageNum, VirtAddr, VirtPageNum};
 use crate::sync::UPSafeCell;
 use crate::trap::{TrapContext, trap_handler};
 use alloc::sync::{Arc, Weak};
+use alloc::string::String;
 use alloc::vec::Vec;
 use log::warn;
 use alloc::vec;
@@ -34,6 +36,12 @@ pub struct TaskControlBlockInner {
     pub children: Vec<Arc<TaskControlBlock>>,
     pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
     pub exit_code: i32,
+    /// received but not yet handled
+    pub signals: SignalFlags,
+    /// stopped by SIGSTOP or SIGTSTP until SIGCONT
+    pub frozen: bool,
+    /// ticks spent running, up to the last switch away
+    pub cpu_time: usize,
 }
 
 impl TaskControlBlockInner {
@@ -76,7 +84,7 @@ impl TaskControlBlock {
 
     pub fn new(elf_data: &[u8]) -> Self {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data).unwrap();
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
@@ -110,6 +118,9 @@ impl TaskControlBlock {
                         Some(Arc::new(Stdout)),
                     ],
                     exit_code: 0,
+                    signals: SignalFlags::empty(),
+                    frozen: false,
+                    cpu_time: 0,
                 })
             },
         };
@@ -163,6 +174,9 @@ impl TaskControlBlock {
                     children: Vec::new(),
                     fd_table: new_fd_table,
                     exit_code: 0,
+                    signals: SignalFlags::empty(),
+                    frozen: false,
+                    cpu_time: 0,
                 })
             },
         });
@@ -177,9 +191,11 @@ impl TaskControlBlock {
         // ---- stop exclusively accessing parent/children PCB automatically
     }
 
-    pub fn exec(&self, elf_data: &[u8]) {
+    /// Replace the address space with the given image and push `args` as argv.
+    /// On error the current address space is left untouched.
+    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) -> Result<(), ElfError> {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data)?;
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
@@ -196,15 +212,36 @@ impl TaskControlBlock {
         inner.user_stack_bottom = VirtAddr::from(user_sp - USER_STACK_SIZE);
         // update base size
         inner.base_size = user_sp;
+        // map the pages holding argv, the rest of the stack stays lazy
+        let argv_base = user_sp - (args.len() + 1) * size_of::<usize>();
+        let strings_size: usize = args.iter().map(|arg| arg.len() + 1).sum();
+        let new_user_sp = (argv_base - strings_size) & !0xf;
+        while inner.user_stack_mapped_va.0 > new_user_sp {
+            inner.new_page_for_stack();
+        }
+        // push argv pointers, then the strings they point to
+        let token = inner.get_user_token();
+        let mut string_ptr = argv_base;
+        for (i, arg) in args.iter().enumerate() {
+            string_ptr -= arg.len() + 1;
+            *translated_refmut(token, (argv_base + i * size_of::<usize>()) as *mut usize) = string_ptr;
+            for (j, c) in arg.as_bytes().iter().chain(&[0]).enumerate() {
+                *translated_refmut(token, (string_ptr + j) as *mut u8) = *c;
+            }
+        }
+        *translated_refmut(token, (argv_base + args.len() * size_of::<usize>()) as *mut usize) = 0;
         // initialize trap_cx
         let trap_cx = inner.get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
             entry_point,
-            user_sp,
+            new_user_sp,
             KERNEL_SPACE.exclusive_access().token(),
             self.kernel_stack.get_top(),
             trap_handler as usize,
         );
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        Ok(())
         // **** stop exclusively accessing inner automatically
     }
 
@@ -235,5 +272,7 @@ impl TaskControlBlock {
 pub enum TaskStatus {
     Ready,
     Running,
+    /// parked on a `WaitQueue`, not in the ready queue
+    Blocked,
     Zombie,
 }
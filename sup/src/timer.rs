@@ -1,22 +1,141 @@
 use riscv::register::time;
+use crate::board::board;
 use crate::config::*;
+use crate::sbi::set_timer;
+use crate::sync::UPSafeCell;
+use crate::task::{TaskControlBlock, wakeup_task};
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use lazy_static::*;
 
 pub fn get_time() -> usize {
     time::read()
 }
 
 pub fn get_time_ms() -> usize {
-    time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
+    time::read() / (board().clock_freq / MSEC_PER_SEC)
 }
 
 
 pub fn get_time_us() -> usize {
-    time::read() / (CLOCK_FREQ / MICRO_PER_SEC)
+    time::read() / (board().clock_freq / MICRO_PER_SEC)
 }
 
+/// Nanoseconds in `ticks` of `time`
+pub fn ticks_to_ns(ticks: usize) -> usize {
+    (ticks as u128 * NSEC_PER_SEC as u128 / board().clock_freq as u128) as usize
+}
+
+pub fn get_time_ns() -> usize {
+    ticks_to_ns(time::read())
+}
+
+fn ms_to_ticks(ms: usize) -> usize {
+    ms * (board().clock_freq / MSEC_PER_SEC)
+}
+
+/// When the first sleeper is due, in ticks
+fn next_deadline() -> Option<usize> {
+    TIMERS
+        .exclusive_access()
+        .peek()
+        .map(|timer| ms_to_ticks(timer.expire_ms))
+}
+
+/// One-shot timer for the end of the time slice of the task about to run, or
+/// for the first sleeper if that is due sooner
 pub fn set_next_trigger() {
-    unsafe {
-        let mtimecmp_addr = (MTIMECMP as usize) as *mut u64;
-        mtimecmp_addr.write_volatile(time::read() as u64 + TIME_INTERVAL);
+    let slice_end = time::read() + TIME_INTERVAL as usize;
+    set_timer(next_deadline().map_or(slice_end, |deadline| deadline.min(slice_end)));
+}
+
+/// Nothing is runnable, so no time slice: only the first sleeper, if any
+pub fn set_idle_trigger() {
+    set_timer(next_deadline().unwrap_or(usize::MAX));
+}
+
+/// Time given to syscalls, like `struct timespec`
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn as_ns(&self) -> usize {
+        self.sec.saturating_mul(NSEC_PER_SEC).saturating_add(self.nsec)
     }
-}
\ No newline at end of file
+    pub fn from_ns(ns: usize) -> Self {
+        Self {
+            sec: ns / NSEC_PER_SEC,
+            nsec: ns % NSEC_PER_SEC,
+        }
+    }
+}
+
+/// Time given to `gettimeofday`, like `struct timeval`
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+/// Clocks of `clock_gettime`
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+
+/// A task to wake at `expire_ms`
+pub struct TimerCondVar {
+    pub expire_ms: usize,
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for TimerCondVar {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire_ms == other.expire_ms
+    }
+}
+impl Eq for TimerCondVar {}
+impl PartialOrd for TimerCondVar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for TimerCondVar {
+    // reversed, so that the heap pops the earliest timer
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire_ms.cmp(&self.expire_ms)
+    }
+}
+
+lazy_static! {
+    static ref TIMERS: UPSafeCell<BinaryHeap<TimerCondVar>> =
+        unsafe { UPSafeCell::new(BinaryHeap::<TimerCondVar>::new()) };
+}
+
+/// Wake `task` once `expire_ms` has passed, if it is still blocked by then
+pub fn add_timer(expire_ms: usize, task: Arc<TaskControlBlock>) {
+    TIMERS
+        .exclusive_access()
+        .push(TimerCondVar { expire_ms, task });
+}
+
+/// Wake every task whose timer has expired, tells if there were any
+pub fn check_timer() -> bool {
+    let current_ms = get_time_ms();
+    let mut timers = TIMERS.exclusive_access();
+    let mut woken = false;
+    while let Some(timer) = timers.peek() {
+        if timer.expire_ms > current_ms {
+            break;
+        }
+        let timer = timers.pop().unwrap();
+        wakeup_task(timer.task);
+        woken = true;
+    }
+    woken
+}
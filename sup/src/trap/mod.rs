@@ -12,7 +12,7 @@ use core::arch::asm;
 use core::arch::global_asm;
 use riscv::register::{
     scause::{self, Exception, Interrupt, Trap},
-    stval,
+    sepc, stval,
     stvec::{self, TrapMode},
 };
 
@@ -40,6 +40,12 @@ pub fn enable_timer_interrupt() {
     }
 }
 
+pub fn enable_external_interrupt() {
+    unsafe {
+        sie::set_sext();
+    }
+}
+
 #[unsafe(no_mangle)]
 /// handle an interrupt, exception, or system call from user space
 pub fn trap_handler() -> ! {
@@ -52,14 +58,24 @@ pub fn trap_handler() -> ! {
             let mut cx = current_trap_cx();
             cx.sepc += 4;
             // get system call return value
-            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14]]);
             // cx is changed during sys_exec, so we have to call it again
             cx = current_trap_cx();
             cx.x[10] = result as usize;
         }
-        Trap::Exception(Exception::StoreFault)
-        | Trap::Exception(Exception::StorePageFault)
+        Trap::Exception(Exception::InstructionFault)
         | Trap::Exception(Exception::LoadFault)
+        | Trap::Exception(Exception::StoreFault) => {
+            info!(
+                "[kernel] {:?} in application at {:#x}{}, bad instruction = {:#x}, kernel killed it.",
+                scause.cause(),
+                stval,
+                pmp_note(stval),
+                cx.sepc
+            );
+            exit_current_and_run_next(-2);
+        }
+        Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::LoadPageFault) => {
             let stval = stval::read();
             let mapped_stack = current_user_mapped_stack();
@@ -84,16 +100,15 @@ pub fn trap_handler() -> ! {
             println!("[kernel] IllegalInstruction in application, kernel killed it.");
             exit_current_and_run_next(-3);
         }
-        Trap::Interrupt(Interrupt::SupervisorSoft) => {
-            // SSI is used for machine timer interrupt
+        Trap::Interrupt(Interrupt::SupervisorTimer) => {
             // info!("Supervisor Timer Interrupt at {}", time::read());
-            // set_next_trigger();
-            unsafe {
-                let sip = sie::read().bits();
-                asm!("csrw sip, {sip}", sip = in(reg) sip ^ 2);
-            }
+            // a slice ended or a sleeper is due, the next task sets the next timer
+            check_timer();
             suspend_current_and_run_next();
         }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            crate::drivers::irq_handler();
+        }
         _ => {
             panic!(
                 "Unsupported trap {:?}, stval = {:#x}!",
@@ -102,6 +117,7 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    handle_signals();
     trap_return();
 }
 
@@ -135,8 +151,26 @@ pub fn trap_return() -> ! {
 pub fn trap_from_kernel() -> ! {
     let c = scause::read().cause();
     let t = stval::read();
+    if let Trap::Exception(Exception::InstructionFault | Exception::LoadFault | Exception::StoreFault) = c {
+        panic!(
+            "[kernel] {:?} at {:#x}{}, sepc = {:#x}!",
+            c,
+            t,
+            pmp_note(t),
+            sepc::read()
+        );
+    }
     panic!(
         "[kernel] Unsupported trap from kernel: {:?}, stval = {:#x}!",
         c, t
     );
 }
+
+/// Explain an access fault at `addr` that the PMP of the machine stage raised
+fn pmp_note(addr: usize) -> &'static str {
+    if (MACHINE_START..SUPERVISOR_START).contains(&addr) {
+        " (the M-mode area, denied by PMP)"
+    } else {
+        ""
+    }
+}
@@ -23,14 +23,6 @@ bitflags! {
     }
 }
 
-macro_rules! wait_for {
-    ($cond:expr) => {
-        while !$cond {
-            core::hint::spin_loop()
-        }
-    };
-}
-
 impl MmioSerialPort {
     /// Creates a new UART interface on the given memory mapped address.
     ///
@@ -80,7 +72,7 @@ impl MmioSerialPort {
             // and enable auxilliary output #2 (used as interrupt line for CPU)
             self_modem_ctrl.write(0x0B);
 
-            // Enable interrupts
+            // Enable the received data available interrupt
             self_int_en.write(0x01);
         }
     }
@@ -89,33 +81,13 @@ impl MmioSerialPort {
         unsafe { LineStsFlags::from_bits_truncate(*self.line_sts.load(Ordering::Relaxed)) }
     }
 
-    /// Sends a byte on the serial port.
-    pub fn send(&mut self, data: u8) {
+    /// Receives a byte if one is waiting.
+    pub fn try_receive(&mut self) -> Option<u8> {
         let self_data = self.data.load(Ordering::Relaxed);
-        unsafe {
-            match data {
-                8 | 0x7F => {
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(8);
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(b' ');
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(8)
-                }
-                _ => {
-                    wait_for!(self.line_sts().contains(LineStsFlags::OUTPUT_EMPTY));
-                    self_data.write(data);
-                }
-            }
-        }
-    }
-
-    /// Receives a byte on the serial port.
-    pub fn receive(&mut self) -> u8 {
-        let self_data = self.data.load(Ordering::Relaxed);
-        unsafe {
-            wait_for!(self.line_sts().contains(LineStsFlags::INPUT_FULL));
-            self_data.read()
+        if self.line_sts().contains(LineStsFlags::INPUT_FULL) {
+            unsafe { Some(self_data.read()) }
+        } else {
+            None
         }
     }
 }
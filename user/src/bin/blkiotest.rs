@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::format;
+use user_lib::{OpenFlags, close, fork, get_time, open, read, remove, waitpid, write};
+
+const WRITERS: usize = 4;
+const BLOCKS: usize = 32;
+
+/// Write a file of `BLOCKS` blocks tagged with `id` and read it back
+fn writer(id: usize) -> i32 {
+    let path = format!("blkiotest{}\0", id);
+    let mut block = [0u8; 512];
+    let fd = open(path.as_str(), OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    for i in 0..BLOCKS {
+        block.fill((id * BLOCKS + i) as u8);
+        assert_eq!(write(fd as usize, &block), 512);
+    }
+    close(fd as usize);
+    let fd = open(path.as_str(), OpenFlags::RDONLY);
+    assert!(fd > 0);
+    for i in 0..BLOCKS {
+        assert_eq!(read(fd as usize, &mut block), 512);
+        assert!(block.iter().all(|&b| b == (id * BLOCKS + i) as u8));
+    }
+    close(fd as usize);
+    remove(path.as_str());
+    0
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // a task that never touches the disk keeps running while the writers sleep on it
+    let spinner = fork();
+    if spinner == 0 {
+        let start = get_time();
+        while get_time() - start < 100 {}
+        return 0;
+    }
+    let mut pids = [0usize; WRITERS];
+    for (id, pid) in pids.iter_mut().enumerate() {
+        let child = fork();
+        if child == 0 {
+            return writer(id);
+        }
+        *pid = child as usize;
+    }
+    let mut exit_code: i32 = 0;
+    for pid in pids.into_iter().chain([spinner as usize]) {
+        assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+        assert_eq!(exit_code, 0);
+    }
+    println!("blkiotest passed!");
+    0
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME, TimeSpec, TimeVal, clock_gettime,
+    gettimeofday, poll,
+};
+
+fn ns(clock_id: usize) -> usize {
+    let mut tp = TimeSpec::default();
+    assert_eq!(clock_gettime(clock_id, &mut tp), 0);
+    assert!(tp.nsec < 1_000_000_000);
+    tp.sec * 1_000_000_000 + tp.nsec
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // the wall clock is past 2020 and agrees with gettimeofday
+    let realtime = ns(CLOCK_REALTIME);
+    assert!(realtime / 1_000_000_000 > 1_577_836_800);
+    let mut tv = TimeVal::default();
+    assert_eq!(gettimeofday(&mut tv), 0);
+    assert!(tv.usec < 1_000_000);
+    assert!(tv.sec.abs_diff(realtime / 1_000_000_000) <= 1);
+    println!("realtime OK");
+
+    // blocking takes monotonic time but next to no CPU time
+    let start = ns(CLOCK_MONOTONIC);
+    let cpu_start = ns(CLOCK_PROCESS_CPUTIME_ID);
+    assert_eq!(poll(&mut [], 100), 0);
+    let slept = ns(CLOCK_MONOTONIC) - start;
+    // the deadline is kept in whole ms
+    assert!(slept >= 99_000_000);
+    assert!(ns(CLOCK_PROCESS_CPUTIME_ID) - cpu_start < slept / 2);
+    println!("monotonic OK");
+
+    // spinning is charged to the process
+    let cpu_start = ns(CLOCK_PROCESS_CPUTIME_ID);
+    let start = ns(CLOCK_MONOTONIC);
+    while ns(CLOCK_MONOTONIC) - start < 50_000_000 {}
+    let spent = ns(CLOCK_PROCESS_CPUTIME_ID) - cpu_start;
+    assert!(spent > 0 && spent <= ns(CLOCK_MONOTONIC) - start);
+    println!("cputime OK");
+
+    let mut tp = TimeSpec::default();
+    assert!(clock_gettime(42, &mut tp) < 0);
+    println!("clocktest passed!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{CLOCK_REALTIME, TimeSpec, clock_gettime};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Year, month (1-12) and day of `days` since 1970-01-01, in the proleptic
+/// Gregorian calendar, counting from March so leap days come last
+fn civil_from_days(days: usize) -> (usize, usize, usize) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as usize;
+    (year, month, day)
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut now = TimeSpec::default();
+    if clock_gettime(CLOCK_REALTIME, &mut now) < 0 {
+        println!("date: no wall clock");
+        return -1;
+    }
+    let days = now.sec / 86_400;
+    let secs = now.sec % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    // 1970-01-01 was a Thursday
+    println!(
+        "{} {} {:2} {:02}:{:02}:{:02} UTC {}",
+        WEEKDAYS[(days + 4) % 7],
+        MONTHS[month - 1],
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        year
+    );
+    0
+}
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{OpenFlags, close, open, read, write};
+
+fn open_dev(path: &str, flags: OpenFlags) -> usize {
+    let fd = open(path, flags);
+    assert!(fd >= 0, "cannot open {}", path);
+    fd as usize
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut buf = [0xffu8; 64];
+
+    // a leading slash is allowed
+    let fd = open_dev("/dev/null\0", OpenFlags::RDWR);
+    assert_eq!(write(fd, b"gone"), 4);
+    assert_eq!(read(fd, &mut buf), 0);
+    close(fd);
+    println!("/dev/null OK");
+
+    let fd = open_dev("dev/zero\0", OpenFlags::RDWR);
+    assert_eq!(read(fd, &mut buf), 64);
+    assert!(buf.iter().all(|&byte| byte == 0));
+    assert_eq!(write(fd, b"gone"), 4);
+    close(fd);
+    println!("/dev/zero OK");
+
+    let fd = open_dev("dev/full\0", OpenFlags::RDWR);
+    buf.fill(0xff);
+    assert_eq!(read(fd, &mut buf), 64);
+    assert!(buf.iter().all(|&byte| byte == 0));
+    assert!(write(fd, b"no room") < 0);
+    close(fd);
+    println!("/dev/full OK");
+
+    let fd = open_dev("dev/console\0", OpenFlags::WRONLY);
+    assert_eq!(write(fd, b"/dev/console OK\n"), 16);
+    close(fd);
+
+    // the root disk starts with the easy-fs super block, reads need not be
+    // aligned and go on where the last one ended
+    let fd = open_dev("dev/vda\0", OpenFlags::RDONLY);
+    let mut magic = [0u8; 4];
+    assert_eq!(read(fd, &mut magic), 4);
+    assert_eq!(u32::from_le_bytes(magic), 0x3b80_0001);
+    let mut total_blocks = [0u8; 4];
+    assert_eq!(read(fd, &mut total_blocks), 4);
+    assert!(u32::from_le_bytes(total_blocks) > 0);
+    let mut block = [0u8; 1024];
+    assert_eq!(read(fd, &mut block), 1024);
+    close(fd);
+    println!("/dev/vda OK");
+
+    assert!(open("dev/nosuchdevice\0", OpenFlags::RDONLY) < 0);
+    println!("devtest passed!");
+    0
+}
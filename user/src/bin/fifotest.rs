@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{OpenFlags, close, fork, mkfifo, open, read, remove, wait, write};
+
+static STR: &str = "Hello, fifo!";
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    remove("fifotest\0");
+    assert_eq!(mkfifo("fifotest\0"), 0);
+    if fork() == 0 {
+        // child process, opens the fifo by name and blocks until the writer comes
+        let fd = open("fifotest\0", OpenFlags::RDONLY);
+        assert!(fd > 0);
+        let mut buffer = [0u8; 32];
+        let len_read = read(fd as usize, &mut buffer) as usize;
+        close(fd as usize);
+        assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
+        println!("Read OK, child process exited!");
+        0
+    } else {
+        let fd = open("fifotest\0", OpenFlags::WRONLY);
+        assert!(fd > 0);
+        assert_eq!(write(fd as usize, STR.as_bytes()), STR.len() as isize);
+        close(fd as usize);
+        let mut child_exit_code: i32 = 0;
+        wait(&mut child_exit_code);
+        assert_eq!(child_exit_code, 0);
+        remove("fifotest\0");
+        println!("fifotest passed!");
+        0
+    }
+}
@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 32];
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = (i as u8).wrapping_mul(0x11);
+    }
+    let base = buf.as_mut_ptr() as usize;
+
+    // loads at odd addresses, the sign of lw and lh is kept
+    let (ld, lw, lwu, lh): (usize, usize, usize, usize);
+    unsafe {
+        asm!(
+            "ld {ld}, 0({p})",
+            "lw {lw}, 8({p})",
+            "lwu {lwu}, 8({p})",
+            "lh {lh}, 12({p})",
+            p = in(reg) base + 1,
+            ld = out(reg) ld,
+            lw = out(reg) lw,
+            lwu = out(reg) lwu,
+            lh = out(reg) lh,
+        );
+    }
+    assert_eq!(ld, 0x8877_6655_4433_2211);
+    assert_eq!(lw, 0xffff_ffff_ccbb_aa99);
+    assert_eq!(lwu, 0xccbb_aa99);
+    assert_eq!(lh, 0xffff_ffff_ffff_eedd);
+    println!("misaligned load OK");
+
+    unsafe {
+        asm!(
+            "sd {d}, 0({p})",
+            "sw {w}, 8({p})",
+            "sh {h}, 12({p})",
+            p = in(reg) base + 17,
+            d = in(reg) 0x0102_0304_0506_0708usize,
+            w = in(reg) 0x090a_0b0cusize,
+            h = in(reg) 0x0d0eusize,
+        );
+    }
+    assert_eq!(&buf[16..32], &[
+        0x10, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x0c, 0x0b, 0x0a, 0x09, 0x0e,
+        0x0d, 0x0f,
+    ]);
+    println!("misaligned store OK");
+
+    // the time CSR counts up, read directly or emulated
+    let (t0, t1): (usize, usize);
+    unsafe {
+        asm!("rdtime {}", out(reg) t0);
+        asm!("csrr {}, time", out(reg) t1);
+    }
+    assert!(t1 >= t0 && t0 > 0);
+    println!("rdtime OK");
+    println!("misaligned passed!");
+    0
+}
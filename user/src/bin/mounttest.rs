@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{OpenFlags, close, mkdir, mount, mv, open, read, remove, umount, write};
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // left over from an earlier run if it failed half way
+    umount("mounttest_mnt\0");
+    remove("mounttest_mnt\0");
+    remove("mounttest_file\0");
+    remove("mounttest_sub/mounttest_dir\0");
+    remove("mounttest_dir/mounttest_sub\0");
+    remove("mounttest_sub\0");
+    remove("mounttest_dir\0");
+    assert_eq!(mkdir("mounttest_mnt\0"), 0);
+
+    assert!(mount("vda\0", "mounttest_mnt\0", "nosuchfs\0", "\0") < 0);
+    assert!(mount("vda\0", "mounttest_nosuchdir\0", "easyfs\0", "\0") < 0);
+    assert!(mount("nosuchdisk\0", "mounttest_mnt\0", "easyfs\0", "\0") < 0);
+    assert!(umount("mounttest_mnt\0") < 0);
+    assert!(umount("/\0") < 0);
+    println!("mount errors OK");
+
+    // the root disk a second time, the same files are seen through both
+    assert_eq!(mount("/dev/vda\0", "/mounttest_mnt\0", "easyfs\0", "\0"), 0);
+    assert!(mount("vda\0", "mounttest_mnt\0", "easyfs\0", "\0") < 0);
+    let fd = open("mounttest_file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, b"through the mount"), 17);
+    close(fd as usize);
+    let fd = open("mounttest_mnt/mounttest_file\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; 32];
+    assert_eq!(read(fd as usize, &mut buf), 17);
+    assert_eq!(&buf[..17], b"through the mount");
+    close(fd as usize);
+    // the root of the mount, not the directory under it
+    assert!(open("mounttest_mnt/mounttest_mnt/../bin/../mounttest_file\0", OpenFlags::RDONLY) >= 0);
+    assert!(remove("mounttest_mnt\0") < 0);
+    println!("mount OK");
+
+    // a directory does not go below itself, not even through the mount
+    assert_eq!(mkdir("mounttest_dir\0"), 0);
+    assert_eq!(mkdir("mounttest_dir/mounttest_sub\0"), 0);
+    assert!(mv("mounttest_dir\0", "mounttest_dir\0") < 0);
+    assert!(mv("mounttest_dir\0", "mounttest_dir/mounttest_sub\0") < 0);
+    assert!(mv("mounttest_dir\0", "mounttest_mnt/mounttest_dir/mounttest_sub\0") < 0);
+    // .. follows a moved directory, so the old parent may go below it now
+    assert_eq!(mv("mounttest_dir/mounttest_sub\0", "mounttest_mnt\0"), 0);
+    assert_eq!(mv("mounttest_dir\0", "mounttest_sub\0"), 0);
+    assert_eq!(remove("mounttest_sub/mounttest_dir\0"), 0);
+    assert_eq!(remove("mounttest_sub\0"), 0);
+    println!("move into itself OK");
+
+    assert_eq!(umount("mounttest_mnt\0"), 0);
+    assert!(open("mounttest_mnt/mounttest_file\0", OpenFlags::RDONLY) < 0);
+    assert!(umount("mounttest_mnt\0") < 0);
+    assert_eq!(remove("mounttest_file\0"), 0);
+    assert_eq!(remove("mounttest_mnt\0"), 0);
+    println!("umount OK");
+    println!("mounttest passed!");
+    0
+}
@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{MqAttr, OpenFlags, fork, mq_close, mq_open, mq_receive, mq_send, wait};
+
+static MESSAGES: &[(&str, u32)] = &[("low", 1), ("high", 5), ("also low", 1)];
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let attr = MqAttr {
+        max_msgs: 4,
+        msg_size: 64,
+    };
+    let mq = mq_open("mqtest\0", OpenFlags::CREATE | OpenFlags::RDWR, Some(&attr));
+    assert!(mq >= 0);
+    let mq = mq as usize;
+    if fork() == 0 {
+        // child process, send all messages through the inherited fd
+        for (msg, prio) in MESSAGES {
+            assert_eq!(mq_send(mq, msg.as_bytes(), *prio), 0);
+        }
+        // messages larger than msg_size are refused
+        assert_eq!(mq_send(mq, &[0u8; 65], 0), -1);
+        mq_close(mq);
+        println!("Send OK, child process exited!");
+        0
+    } else {
+        let mut child_exit_code: i32 = 0;
+        wait(&mut child_exit_code);
+        assert_eq!(child_exit_code, 0);
+        // highest priority first, then in sending order
+        let mut buffer = [0u8; 64];
+        let mut prio = 0u32;
+        for (expected, expected_prio) in [("high", 5), ("low", 1), ("also low", 1)] {
+            let len = mq_receive(mq, &mut buffer, &mut prio);
+            assert_eq!(core::str::from_utf8(&buffer[..len as usize]).unwrap(), expected);
+            assert_eq!(prio, expected_prio);
+        }
+        mq_close(mq);
+        println!("mqtest passed!");
+        0
+    }
+}
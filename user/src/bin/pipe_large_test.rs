@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    F_GETPIPE_SZ, F_SETPIPE_SZ, OpenFlags, close, fork, get_time, ioctl, pipe, pipe2, read, wait,
+    write,
+};
+
+const LENGTH: usize = 4 << 20;
+const CHUNK: usize = 4096;
+const EAGAIN: isize = -11;
+const EBUSY: isize = -16;
+const EPIPE: isize = -32;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let start = get_time();
+    if fork() == 0 {
+        // child process, read everything and check the pattern
+        close(pipe_fd[1]);
+        let mut buffer = [0u8; CHUNK];
+        let mut total = 0usize;
+        loop {
+            let len = read(pipe_fd[0], &mut buffer);
+            if len == 0 {
+                break;
+            }
+            for (i, byte) in buffer[..len as usize].iter().enumerate() {
+                assert_eq!(*byte, ((total + i) % 251) as u8);
+            }
+            total += len as usize;
+        }
+        close(pipe_fd[0]);
+        assert_eq!(total, LENGTH);
+        0
+    } else {
+        close(pipe_fd[0]);
+        let mut buffer = [0u8; CHUNK];
+        for offset in (0..LENGTH).step_by(CHUNK) {
+            for (i, byte) in buffer.iter_mut().enumerate() {
+                *byte = ((offset + i) % 251) as u8;
+            }
+            assert_eq!(write(pipe_fd[1], &buffer), CHUNK as isize);
+        }
+        close(pipe_fd[1]);
+        let mut child_exit_code: i32 = 0;
+        wait(&mut child_exit_code);
+        assert_eq!(child_exit_code, 0);
+        println!("{} KiB through a pipe in {} ms", LENGTH >> 10, get_time() - start);
+
+        // an empty non-blocking pipe does not block, a pipe without readers breaks
+        pipe2(&mut pipe_fd, OpenFlags::NONBLOCK);
+        assert_eq!(read(pipe_fd[0], &mut buffer), EAGAIN);
+        close(pipe_fd[0]);
+        assert_eq!(write(pipe_fd[1], &buffer), EPIPE);
+        close(pipe_fd[1]);
+
+        // the capacity grows in whole pages, and never below what is queued
+        pipe2(&mut pipe_fd, OpenFlags::NONBLOCK);
+        assert_eq!(ioctl(pipe_fd[0], F_GETPIPE_SZ, 0), CHUNK as isize);
+        assert_eq!(ioctl(pipe_fd[1], F_SETPIPE_SZ, CHUNK + 1), 2 * CHUNK as isize);
+        assert_eq!(write(pipe_fd[1], &buffer), CHUNK as isize);
+        assert_eq!(write(pipe_fd[1], &buffer), CHUNK as isize);
+        assert_eq!(write(pipe_fd[1], &buffer), EAGAIN);
+        assert_eq!(ioctl(pipe_fd[1], F_SETPIPE_SZ, CHUNK), EBUSY);
+        assert_eq!(read(pipe_fd[0], &mut buffer), CHUNK as isize);
+        assert_eq!(ioctl(pipe_fd[1], F_SETPIPE_SZ, CHUNK), CHUNK as isize);
+        assert_eq!(read(pipe_fd[0], &mut buffer), CHUNK as isize);
+        close(pipe_fd[0]);
+        close(pipe_fd[1]);
+        println!("pipe_large_test passed!");
+        0
+    }
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    POLLHUP, POLLIN, POLLNVAL, POLLOUT, PollFd, close, fork, get_time, pipe, poll, read, sleep,
+    wait, write,
+};
+
+static STR: &str = "Hello, poll!";
+
+fn pollfd(fd: usize, events: u16) -> PollFd {
+    PollFd {
+        fd: fd as i32,
+        events,
+        revents: 0,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut first = [0usize; 2];
+    let mut second = [0usize; 2];
+    pipe(&mut first);
+    pipe(&mut second);
+
+    // nothing to read yet, the timeout expires
+    let mut fds = [pollfd(first[0], POLLIN), pollfd(second[0], POLLIN)];
+    let start = get_time();
+    assert_eq!(poll(&mut fds, 50), 0);
+    assert!(get_time() - start >= 50);
+    assert_eq!(fds[0].revents | fds[1].revents, 0);
+
+    // an empty pipe has room to write
+    let mut fds = [pollfd(first[1], POLLOUT)];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert_eq!(fds[0].revents, POLLOUT);
+
+    if fork() == 0 {
+        close(first[0]);
+        close(second[0]);
+        sleep(20);
+        // only the second pipe gets data
+        assert_eq!(write(second[1], STR.as_bytes()), STR.len() as isize);
+        close(first[1]);
+        close(second[1]);
+        return 0;
+    }
+    close(first[1]);
+    close(second[1]);
+    // block without a timeout until the child writes
+    let mut fds = [pollfd(first[0], POLLIN), pollfd(second[0], POLLIN)];
+    assert!(poll(&mut fds, -1) >= 1);
+    assert_eq!(fds[1].revents & POLLIN, POLLIN);
+    let mut buffer = [0u8; 32];
+    let len = read(second[0], &mut buffer) as usize;
+    assert_eq!(core::str::from_utf8(&buffer[..len]).unwrap(), STR);
+
+    let mut exit_code: i32 = 0;
+    wait(&mut exit_code);
+    assert_eq!(exit_code, 0);
+    // every writer is gone
+    let mut fds = [pollfd(first[0], POLLIN), pollfd(second[0], POLLIN)];
+    assert_eq!(poll(&mut fds, -1), 2);
+    assert_eq!(fds[0].revents & POLLHUP, POLLHUP);
+    assert_eq!(fds[1].revents & POLLHUP, POLLHUP);
+    close(first[0]);
+    close(second[0]);
+
+    // closed fds are reported, negative ones ignored
+    let mut fds = [pollfd(first[0], POLLIN), PollFd { fd: -1, events: POLLIN, revents: 0 }];
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert_eq!(fds[0].revents, POLLNVAL);
+    assert_eq!(fds[1].revents, 0);
+    println!("polltest passed!");
+    0
+}
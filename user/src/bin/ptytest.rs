@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::format;
+use alloc::vec::Vec;
+use user_lib::{
+    OpenFlags, TIOCGPTN, close, dup, exec, fork, ioctl, open, openpty, read, waitpid, write,
+};
+
+/// Read from the master until `expected` shows up, return all that was read
+fn read_until(master: usize, expected: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut buf = [0u8; 64];
+    while !output.windows(expected.len()).any(|w| w == expected) {
+        let len = read(master, &mut buf);
+        assert!(len > 0, "pty master closed before {:?}", expected);
+        output.extend_from_slice(&buf[..len as usize]);
+    }
+    output
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut fds = [0usize; 2];
+    assert_eq!(openpty(&mut fds), 0);
+    let (master, slave) = (fds[0], fds[1]);
+    let mut buf = [0u8; 64];
+
+    // the slave gets a line once it is ended, the master sees the echo
+    assert_eq!(write(master, b"hello\r"), 6);
+    assert_eq!(read(slave, &mut buf), 6);
+    assert_eq!(&buf[..6], b"hello\n");
+    assert_eq!(read_until(master, b"\r\n"), b"hello\r\n");
+    // erase and kill are done before the slave reads
+    assert_eq!(write(master, b"ab\x7fc\x15xyz\n"), 9);
+    assert_eq!(read(slave, &mut buf), 4);
+    assert_eq!(&buf[..4], b"xyz\n");
+    read_until(master, b"xyz\r\n");
+    // output of the slave reaches the master
+    assert_eq!(write(slave, b"out\n"), 4);
+    assert_eq!(read_until(master, b"out\r\n"), b"out\r\n");
+    println!("line discipline OK");
+
+    // a closed master hangs up its slave, which can not be opened again
+    let mut index: u32 = 0;
+    assert_eq!(ioctl(master, TIOCGPTN, &mut index as *mut _ as usize), 0);
+    close(master);
+    assert_eq!(read(slave, &mut buf), 0);
+    assert!(write(slave, b"x") < 0);
+    assert!(open(format!("dev/pts/{}\0", index).as_str(), OpenFlags::RDWR) < 0);
+    close(slave);
+    println!("hangup OK");
+
+    // drive the shell through a pty
+    assert_eq!(openpty(&mut fds), 0);
+    let (master, slave) = (fds[0], fds[1]);
+    let pid = fork();
+    if pid == 0 {
+        close(0);
+        assert_eq!(dup(slave), 0);
+        close(1);
+        assert_eq!(dup(slave), 1);
+        close(slave);
+        close(master);
+        exec("user_shell\0", &[core::ptr::null::<u8>()]);
+        panic!("exec user_shell failed");
+    }
+    close(slave);
+    read_until(master, b">> ");
+    write(master, b"hello_world\n");
+    read_until(master, b"Hello world from user mode program!\r\n");
+    read_until(master, b"exited with code 0\r\n>> ");
+    // end of file makes the shell quit
+    write(master, b"\x04");
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(master);
+    println!("shell OK");
+    println!("ptytest passed!");
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{GRND_NONBLOCK, OpenFlags, close, getrandom, open, read, write};
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // two calls never give the same bytes
+    let mut a = [0u8; 64];
+    let mut b = [0u8; 64];
+    assert_eq!(getrandom(&mut a, 0), 64);
+    assert_eq!(getrandom(&mut b, GRND_NONBLOCK), 64);
+    assert_ne!(a, b);
+    assert!(getrandom(&mut a, 0x100) < 0);
+    println!("getrandom OK");
+
+    // about half of the bits are set
+    let mut buf = [0u8; 4096];
+    let fd = open("dev/urandom\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(read(fd as usize, &mut buf), buf.len() as isize);
+    close(fd as usize);
+    let ones: u32 = buf.iter().map(|byte| byte.count_ones()).sum();
+    let bits = buf.len() as u32 * 8;
+    assert!(ones > bits * 45 / 100 && ones < bits * 55 / 100);
+    println!("/dev/urandom OK");
+
+    // writing mixes into the pool, reading goes on
+    let fd = open("dev/random\0", OpenFlags::RDWR);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, b"not much entropy"), 16);
+    assert_eq!(read(fd as usize, &mut a), 64);
+    assert_ne!(a, b);
+    close(fd as usize);
+    println!("randomtest passed!");
+    0
+}
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use user_lib::{args, exec, exit, fork, waitpid};
+
+/// The most argv the kernel takes, `ARG_MAX` in its config
+const ARG_MAX: usize = 4096;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let argv = args();
+    if argv.len() > 1 {
+        // run by the kernel for shebangtest_script
+        assert_eq!(
+            argv,
+            [
+                "/bin/shebangtest",
+                "-i",
+                "bin/shebangtest_script",
+                "one",
+                "two"
+            ]
+        );
+        return 0;
+    }
+
+    let pid = fork();
+    if pid == 0 {
+        let args = [
+            "shebangtest_script\0".as_ptr(),
+            "one\0".as_ptr(),
+            "two\0".as_ptr(),
+            core::ptr::null(),
+        ];
+        exec("shebangtest_script\0", &args);
+        exit(-1);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("#! argv OK");
+
+    // one argument too long, and too many short ones
+    let mut long = vec![b'a'; ARG_MAX];
+    long.push(0);
+    assert_eq!(
+        exec("shebangtest\0", &[long.as_ptr(), core::ptr::null()]),
+        -1
+    );
+    let mut many: Vec<*const u8> = vec!["a\0".as_ptr(); ARG_MAX / 8];
+    many.push(core::ptr::null());
+    assert_eq!(exec("shebangtest\0", &many), -1);
+    println!("ARG_MAX OK");
+    println!("shebangtest passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fork, shm_map, shm_open, shm_unmap, wait};
+
+static STR: &str = "Hello, shared memory!";
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let fd = shm_open("shmtest\0", 4096);
+    assert!(fd > 0);
+    // opening again by name gives the same object, which cannot grow
+    let again = shm_open("shmtest\0", 4096);
+    assert!(again > 0);
+    assert_eq!(shm_open("shmtest\0", 8192), -1);
+    let addr = shm_map(fd as usize);
+    assert!(addr > 0);
+    let buffer = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, 4096) };
+    if fork() == 0 {
+        // child process, the mapping is inherited
+        buffer[..STR.len()].copy_from_slice(STR.as_bytes());
+        assert_eq!(shm_unmap(addr as usize), 0);
+        println!("Write OK, child process exited!");
+        0
+    } else {
+        let mut child_exit_code: i32 = 0;
+        wait(&mut child_exit_code);
+        assert_eq!(child_exit_code, 0);
+        assert_eq!(core::str::from_utf8(&buffer[..STR.len()]).unwrap(), STR);
+        // the other fd maps the same memory
+        let other = shm_map(again as usize);
+        assert!(other > 0);
+        let other_buffer = unsafe { core::slice::from_raw_parts(other as *const u8, 4096) };
+        assert_eq!(&other_buffer[..STR.len()], STR.as_bytes());
+        assert_eq!(shm_unmap(other as usize), 0);
+        assert_eq!(shm_unmap(addr as usize), 0);
+        // the last fd and mapping gone, the object is freed and a new one made
+        close(fd as usize);
+        close(again as usize);
+        let fd = shm_open("shmtest\0", 8192);
+        assert!(fd > 0);
+        let addr = shm_map(fd as usize);
+        assert!(addr > 0);
+        let buffer = unsafe { core::slice::from_raw_parts(addr as *const u8, 8192) };
+        assert!(buffer.iter().all(|&byte| byte == 0));
+        assert_eq!(shm_unmap(addr as usize), 0);
+        close(fd as usize);
+        println!("shmtest passed!");
+        0
+    }
+}
@@ -0,0 +1,136 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    AF_UNIX, SOCK_DGRAM, SOCK_NONBLOCK, SOCK_STREAM, accept, bind, close, connect, fork, listen,
+    read, remove, socket, socketpair, wait, write,
+};
+
+static REQUEST: &str = "ping";
+static REPLY: &str = "pong";
+
+fn wait_child() {
+    let mut exit_code: i32 = 0;
+    wait(&mut exit_code);
+    assert_eq!(exit_code, 0);
+}
+
+fn test_stream_pair() {
+    let mut sv = [0usize; 2];
+    assert_eq!(socketpair(AF_UNIX, SOCK_STREAM, &mut sv), 0);
+    if fork() == 0 {
+        close(sv[0]);
+        let mut buffer = [0u8; 16];
+        let len = read(sv[1], &mut buffer) as usize;
+        assert_eq!(&buffer[..len], REQUEST.as_bytes());
+        assert_eq!(write(sv[1], REPLY.as_bytes()), REPLY.len() as isize);
+        close(sv[1]);
+        user_lib::exit(0);
+    }
+    close(sv[1]);
+    assert_eq!(write(sv[0], REQUEST.as_bytes()), REQUEST.len() as isize);
+    let mut buffer = [0u8; 16];
+    let len = read(sv[0], &mut buffer) as usize;
+    assert_eq!(&buffer[..len], REPLY.as_bytes());
+    // the peer is gone
+    assert_eq!(read(sv[0], &mut buffer), 0);
+    close(sv[0]);
+    wait_child();
+    println!("stream socketpair OK");
+}
+
+fn test_dgram_pair() {
+    let mut sv = [0usize; 2];
+    assert_eq!(socketpair(AF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, &mut sv), 0);
+    assert_eq!(write(sv[0], b"first"), 5);
+    assert_eq!(write(sv[0], b"second"), 6);
+    // message boundaries are kept
+    let mut buffer = [0u8; 16];
+    assert_eq!(read(sv[1], &mut buffer), 5);
+    assert_eq!(&buffer[..5], b"first");
+    assert_eq!(read(sv[1], &mut buffer[..3]), 3);
+    assert_eq!(&buffer[..3], b"sec");
+    // EAGAIN
+    assert_eq!(read(sv[1], &mut buffer), -11);
+    close(sv[0]);
+    close(sv[1]);
+    println!("datagram socketpair OK");
+}
+
+fn test_stream_bound() {
+    remove("sockettest\0");
+    let server = socket(AF_UNIX, SOCK_STREAM);
+    assert!(server > 0);
+    let server = server as usize;
+    assert_eq!(bind(server, "sockettest\0"), 0);
+    // the address is taken
+    let other = socket(AF_UNIX, SOCK_STREAM) as usize;
+    assert_eq!(bind(other, "sockettest\0"), -1);
+    close(other);
+    assert_eq!(listen(server, 4), 0);
+    for _ in 0..2 {
+        if fork() == 0 {
+            let client = socket(AF_UNIX, SOCK_STREAM) as usize;
+            assert_eq!(connect(client, "sockettest\0"), 0);
+            assert_eq!(write(client, REQUEST.as_bytes()), REQUEST.len() as isize);
+            let mut buffer = [0u8; 16];
+            let len = read(client, &mut buffer) as usize;
+            assert_eq!(&buffer[..len], REPLY.as_bytes());
+            close(client);
+            user_lib::exit(0);
+        }
+    }
+    // each connection gets its own buffers
+    for _ in 0..2 {
+        let conn = accept(server);
+        assert!(conn > 0);
+        let conn = conn as usize;
+        let mut buffer = [0u8; 16];
+        let len = read(conn, &mut buffer) as usize;
+        assert_eq!(&buffer[..len], REQUEST.as_bytes());
+        assert_eq!(write(conn, REPLY.as_bytes()), REPLY.len() as isize);
+        close(conn);
+    }
+    wait_child();
+    wait_child();
+    close(server);
+    // nobody listens anymore
+    let client = socket(AF_UNIX, SOCK_STREAM) as usize;
+    assert_eq!(connect(client, "sockettest\0"), -1);
+    close(client);
+    remove("sockettest\0");
+    println!("bound stream socket OK");
+}
+
+fn test_dgram_bound() {
+    remove("sockettest\0");
+    let server = socket(AF_UNIX, SOCK_DGRAM) as usize;
+    assert_eq!(bind(server, "sockettest\0"), 0);
+    if fork() == 0 {
+        let client = socket(AF_UNIX, SOCK_DGRAM) as usize;
+        assert_eq!(connect(client, "sockettest\0"), 0);
+        assert_eq!(write(client, REQUEST.as_bytes()), REQUEST.len() as isize);
+        close(client);
+        user_lib::exit(0);
+    }
+    let mut buffer = [0u8; 16];
+    let len = read(server, &mut buffer) as usize;
+    assert_eq!(&buffer[..len], REQUEST.as_bytes());
+    wait_child();
+    close(server);
+    remove("sockettest\0");
+    println!("bound datagram socket OK");
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    test_stream_pair();
+    test_dgram_pair();
+    test_stream_bound();
+    test_dgram_bound();
+    println!("sockettest passed!");
+    0
+}
@@ -0,0 +1,117 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::format;
+use user_lib::{OpenFlags, close, mkdir, mount, mv, open, read, remove, rename, umount, write};
+
+const FILES: usize = 1000;
+const PAGE_SIZE: usize = 4096;
+
+fn write_file(path: &str, data: &[u8]) -> isize {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let written = write(fd as usize, data);
+    close(fd as usize);
+    written
+}
+
+fn read_file(path: &str, buf: &mut [u8]) -> isize {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return fd;
+    }
+    let mut total = 0;
+    loop {
+        let len = read(fd as usize, &mut buf[total..]);
+        if len <= 0 {
+            break;
+        }
+        total += len as usize;
+    }
+    close(fd as usize);
+    total as isize
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 4 * PAGE_SIZE];
+
+    // lots of small files
+    for i in 0..FILES {
+        let path = format!("/tmp/tmpfstest{}\0", i);
+        let data = format!("file {}", i);
+        assert_eq!(write_file(&path, data.as_bytes()), data.len() as isize);
+    }
+    for i in 0..FILES {
+        let path = format!("/tmp/tmpfstest{}\0", i);
+        let data = format!("file {}", i);
+        assert_eq!(read_file(&path, &mut buf), data.len() as isize);
+        assert_eq!(&buf[..data.len()], data.as_bytes());
+        assert_eq!(remove(&path), 0);
+    }
+    assert!(open("/tmp/tmpfstest0\0", OpenFlags::RDONLY) < 0);
+    println!("{} files OK", FILES);
+
+    // a file over several pages, written and read across page ends
+    let mut data = [0u8; 3 * PAGE_SIZE + 100];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = (i % 251) as u8;
+    }
+    assert_eq!(write_file("/tmp/tmpfstest_big\0", &data), data.len() as isize);
+    assert_eq!(read_file("/tmp/tmpfstest_big\0", &mut buf), data.len() as isize);
+    assert_eq!(&buf[..data.len()], &data[..]);
+    // truncated on open with CREATE
+    assert_eq!(write_file("/tmp/tmpfstest_big\0", b"short"), 5);
+    assert_eq!(read_file("/tmp/tmpfstest_big\0", &mut buf), 5);
+    // still readable through an open file once unlinked
+    let fd = open("/tmp/tmpfstest_big\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    assert_eq!(remove("/tmp/tmpfstest_big\0"), 0);
+    assert_eq!(read(fd as usize, &mut buf), 5);
+    assert_eq!(&buf[..5], b"short");
+    close(fd as usize);
+    println!("big file OK");
+
+    // directories, rename and move
+    assert_eq!(mkdir("/tmp/tmpfstest_dir\0"), 0);
+    assert!(mkdir("/tmp/tmpfstest_dir\0") < 0);
+    assert_eq!(write_file("/tmp/tmpfstest_dir/a\0", b"in dir"), 6);
+    assert_eq!(rename("/tmp/tmpfstest_dir/a\0", "b\0"), 0);
+    assert!(open("/tmp/tmpfstest_dir/a\0", OpenFlags::RDONLY) < 0);
+    assert_eq!(read_file("/tmp/tmpfstest_dir/b\0", &mut buf), 6);
+    assert_eq!(mv("/tmp/tmpfstest_dir/b\0", "/tmp\0"), 0);
+    assert_eq!(read_file("/tmp/b\0", &mut buf), 6);
+    assert_eq!(mv("/tmp/b\0", "/tmp/tmpfstest_dir\0"), 0);
+    // not below itself, not onto another file system
+    assert!(mv("/tmp/tmpfstest_dir\0", "/tmp/tmpfstest_dir\0") < 0);
+    assert!(mv("/tmp/tmpfstest_dir/b\0", "/\0") < 0);
+    // a directory goes with what is in it
+    assert_eq!(remove("/tmp/tmpfstest_dir\0"), 0);
+    assert!(open("/tmp/tmpfstest_dir/b\0", OpenFlags::RDONLY) < 0);
+    println!("directories OK");
+
+    // the size limit
+    assert_eq!(mkdir("/tmp/tmpfstest_small\0"), 0);
+    assert!(mount("tmpfs\0", "/tmp/tmpfstest_small\0", "tmpfs\0", "size=bad\0") < 0);
+    assert_eq!(mount("tmpfs\0", "/tmp/tmpfstest_small\0", "tmpfs\0", "size=8k\0"), 0);
+    assert_eq!(write_file("/tmp/tmpfstest_small/f\0", &data), 2 * PAGE_SIZE as isize);
+    let fd = open("/tmp/tmpfstest_small/f\0", OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, &data[..2 * PAGE_SIZE]), 2 * PAGE_SIZE as isize);
+    assert!(write(fd as usize, b"full") < 0);
+    close(fd as usize);
+    // room again once the file is gone
+    assert_eq!(remove("/tmp/tmpfstest_small/f\0"), 0);
+    assert_eq!(write_file("/tmp/tmpfstest_small/g\0", &data[..PAGE_SIZE]), PAGE_SIZE as isize);
+    assert_eq!(umount("/tmp/tmpfstest_small\0"), 0);
+    assert_eq!(remove("/tmp/tmpfstest_small\0"), 0);
+    println!("size limit OK");
+
+    println!("tmpfstest passed!");
+    0
+}
@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    ECHO, EINTR, ENOTTY, ICANON, ISIG, OpenFlags, SIGCONT, SIGINT, SIGKILL, SIGSTOP, TCGETS,
+    Termios, VMIN, close, exit, fork, ioctl, kill, mq_close, mq_open, mq_receive, pipe, sleep,
+    tcgetattr, tcsetattr, waitpid, yield_,
+};
+
+fn spin_child() -> usize {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    pid as usize
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // the console starts in canonical mode with echo
+    let mut termios = Termios {
+        c_iflag: 0,
+        c_oflag: 0,
+        c_cflag: 0,
+        c_lflag: 0,
+        c_line: 0,
+        c_cc: [0; 19],
+    };
+    assert_eq!(tcgetattr(0, &mut termios), 0);
+    let saved = termios;
+    assert_eq!(termios.c_lflag & (ICANON | ECHO | ISIG), ICANON | ECHO | ISIG);
+
+    // switch to raw mode and back
+    termios.c_lflag &= !(ICANON | ECHO | ISIG);
+    termios.c_cc[VMIN] = 1;
+    assert_eq!(tcsetattr(0, &termios), 0);
+    let mut current = saved;
+    assert_eq!(tcgetattr(1, &mut current), 0);
+    assert_eq!(current.c_lflag & (ICANON | ECHO | ISIG), 0);
+    assert_eq!(tcsetattr(0, &saved), 0);
+    println!("termios OK");
+
+    // only terminals take ioctls
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(ioctl(pipe_fd[0], TCGETS, &mut termios as *mut _ as usize), ENOTTY);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // default actions of signals
+    let mut exit_code: i32 = 0;
+    let pid = spin_child();
+    assert_eq!(kill(pid, SIGINT), 0);
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, -(SIGINT as i32));
+    let pid = spin_child();
+    assert_eq!(kill(pid, SIGSTOP), 0);
+    assert_eq!(kill(pid, SIGCONT), 0);
+    assert_eq!(kill(pid, SIGKILL), 0);
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    assert_eq!(kill(pid, SIGINT), -1);
+    // initproc reaps orphans, it cannot be ended or stopped
+    assert_eq!(kill(1, SIGKILL), -1);
+    assert_eq!(kill(1, SIGSTOP), -1);
+    assert_eq!(kill(1, 0), 0);
+    println!("signals OK");
+
+    // a stop takes a process out of a blocking wait
+    let mq = mq_open("ttytest\0", OpenFlags::CREATE | OpenFlags::RDWR, None);
+    assert!(mq >= 0);
+    let pid = fork();
+    if pid == 0 {
+        let mut buf = [0u8; 256];
+        let mut prio = 0;
+        exit(if mq_receive(mq as usize, &mut buf, &mut prio) == EINTR { 0 } else { 1 });
+    }
+    sleep(10);
+    assert_eq!(kill(pid as usize, SIGSTOP), 0);
+    assert_eq!(kill(pid as usize, SIGCONT), 0);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    mq_close(mq as usize);
+    println!("EINTR OK");
+    println!("ttytest passed!");
+    0
+}
@@ -7,57 +7,61 @@ extern crate alloc;
 #[macro_use]
 extern crate user_lib;
 
-const LF: u8 = 0x0au8;
-const CR: u8 = 0x0du8;
-const DL: u8 = 0x7fu8;
-const BS: u8 = 0x08u8;
+const STDIN: usize = 0;
 
+use alloc::format;
 use alloc::string::String;
-use user_lib::console::getchar;
-use user_lib::{exec, fork, waitpid};
+use alloc::vec::Vec;
+use user_lib::{EINTR, exec, fork, read, tcsetpgrp, waitpid};
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
     println!("Rust user shell");
-    let mut line: String = String::new();
-    print!(">> ");
+    // the terminal edits the line, a read returns it once Enter is hit
+    let mut buf = [0u8; 256];
     loop {
-        let c = getchar();
-        match c {
-            LF | CR => {
-                println!("");
-                if !line.is_empty() {
-                    line.push('\0');
-                    let pid = fork();
-                    if pid == 0 {
-                        // child process
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
-                        }
-                        unreachable!();
-                    } else {
-                        let mut exit_code: i32 = 0;
-                        let exit_pid = waitpid(pid as usize, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!("Shell: Process {} exited with code {}", pid, exit_code);
-                    }
-                    line.clear();
-                }
-                print!(">> ");
-            }
-            BS | DL => {
-                if !line.is_empty() {
-                    print!("{}", BS as char);
-                    print!(" ");
-                    print!("{}", BS as char);
-                    line.pop();
-                }
-            }
-            _ => {
-                print!("{}", c as char);
-                line.push(c as char);
+        print!(">> ");
+        let len = read(STDIN, &mut buf);
+        if len == 0 {
+            // end of file
+            println!("");
+            return 0;
+        }
+        if len == EINTR {
+            // a signal came in while waiting, prompt again
+            println!("");
+            continue;
+        }
+        if len < 0 {
+            continue;
+        }
+        let line = String::from_utf8_lossy(&buf[..len as usize]);
+        // split into NUL terminated arguments
+        let args: Vec<String> = line
+            .split_whitespace()
+            .map(|arg| format!("{}\0", arg))
+            .collect();
+        if args.is_empty() {
+            continue;
+        }
+        let mut args_addr: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+        args_addr.push(core::ptr::null::<u8>());
+        let pid = fork();
+        if pid == 0 {
+            // child process
+            if exec(args[0].as_str(), args_addr.as_slice()) == -1 {
+                println!("Error when executing!");
+                return -4;
             }
+            unreachable!();
+        } else {
+            // Ctrl-C goes to the command while it runs
+            tcsetpgrp(STDIN, pid as usize);
+            let mut exit_code: i32 = 0;
+            let exit_pid = waitpid(pid as usize, &mut exit_code);
+            tcsetpgrp(STDIN, 0);
+            assert_eq!(pid, exit_pid);
+            println!("Shell: Process {} exited with code {}", pid, exit_code);
         }
     }
 }
@@ -11,8 +11,16 @@ extern crate user_lib;
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("filetest_simple\0", "\0", "\0", "\0", 0),
     ("cat_filea\0", "\0", "\0", "\0", 0),
+    ("clocktest\0", "\0", "\0", "\0", 0),
+    ("randomtest\0", "\0", "\0", "\0", 0),
+    ("devtest\0", "\0", "\0", "\0", 0),
+    ("mounttest\0", "\0", "\0", "\0", 0),
+    ("tmpfstest\0", "\0", "\0", "\0", 0),
+    ("blkiotest\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
+    ("fifotest\0", "\0", "\0", "\0", 0),
+    ("sockettest\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
@@ -20,6 +28,14 @@ static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("hello_world\0", "\0", "\0", "\0", 0),
     ("huge_write\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("misaligned\0", "\0", "\0", "\0", 0),
+    ("pipe_large_test\0", "\0", "\0", "\0", 0),
+    ("polltest\0", "\0", "\0", "\0", 0),
+    ("ttytest\0", "\0", "\0", "\0", 0),
+    ("ptytest\0", "\0", "\0", "\0", 0),
+    ("mqtest\0", "\0", "\0", "\0", 0),
+    ("shmtest\0", "\0", "\0", "\0", 0),
+    ("shebangtest\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
@@ -65,7 +81,7 @@ fn run_tests(tests: &[(&str, &str, &str, &str, i32)]) -> i32 {
 
         let pid = fork();
         if pid == 0 {
-            exec(test.0);
+            exec(test.0, &arr[..]);
             panic!("unreachable!");
         } else {
             let mut exit_code: i32 = Default::default();
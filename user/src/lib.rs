@@ -9,13 +9,35 @@ mod mm;
 mod sync;
 mod syscall;
 
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+static ARGV: AtomicUsize = AtomicUsize::new(0);
+
 #[unsafe(no_mangle)]
 #[unsafe(link_section = ".text.entry")]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
     mm::init();
+    ARGC.store(argc, Ordering::Relaxed);
+    ARGV.store(argv, Ordering::Relaxed);
     exit(main());
 }
 
+/// Arguments this program was exec'd with, argv[0] first
+pub fn args() -> Vec<&'static str> {
+    let argv = ARGV.load(Ordering::Relaxed) as *const *const u8;
+    (0..ARGC.load(Ordering::Relaxed))
+        .map(|i| unsafe {
+            let start = *argv.add(i);
+            let len = (0usize..).find(|j| *start.add(*j) == 0).unwrap();
+            core::str::from_utf8(core::slice::from_raw_parts(start, len)).unwrap()
+        })
+        .collect()
+}
+
 #[linkage = "weak"]
 #[unsafe(no_mangle)]
 fn main() -> i32 {
@@ -30,9 +52,100 @@ bitflags! {
         const RDWR = 1 << 1;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        const NONBLOCK = 1 << 11;
     }
 }
 
+/// Limits of a message queue created by `mq_open`
+#[repr(C)]
+pub struct MqAttr {
+    pub max_msgs: usize,
+    pub msg_size: usize,
+}
+
+pub const AF_UNIX: usize = 1;
+pub const SOCK_STREAM: usize = 1;
+pub const SOCK_DGRAM: usize = 2;
+/// or-ed into the socket type, same bit as `OpenFlags::NONBLOCK`
+pub const SOCK_NONBLOCK: usize = 1 << 11;
+
+/// One fd watched by `poll`, like `struct pollfd`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: u16,
+    pub revents: u16,
+}
+
+pub const POLLIN: u16 = 1 << 0;
+pub const POLLOUT: u16 = 1 << 2;
+pub const POLLERR: u16 = 1 << 3;
+pub const POLLHUP: u16 = 1 << 4;
+pub const POLLNVAL: u16 = 1 << 5;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+/// Clocks of `clock_gettime`
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+
+/// Flags of `getrandom`, none of them changes anything here
+pub const GRND_NONBLOCK: u32 = 1;
+pub const GRND_RANDOM: u32 = 2;
+
+/// `struct termios` of the kernel, see `tcgetattr`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; 19],
+}
+
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TIOCGPGRP: usize = 0x540f;
+pub const TIOCSPGRP: usize = 0x5410;
+/// number of the slave of a pty master
+pub const TIOCGPTN: usize = 0x8004_5430;
+/// capacity of a pipe, the set one takes it in `arg` and rounds it up to pages
+pub const F_SETPIPE_SZ: usize = 1031;
+pub const F_GETPIPE_SZ: usize = 1032;
+pub const ICRNL: u32 = 0o400;
+pub const OPOST: u32 = 0o1;
+pub const ISIG: u32 = 0o1;
+pub const ICANON: u32 = 0o2;
+pub const ECHO: u32 = 0o10;
+pub const VMIN: usize = 6;
+/// a blocking call was interrupted by a signal
+pub const EINTR: isize = -4;
+/// not a terminal
+pub const ENOTTY: isize = -25;
+
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGKILL: usize = 9;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+
 use syscall::*;
 
 pub fn open(path: &str, flags: OpenFlags) -> isize {
@@ -41,6 +154,10 @@ pub fn open(path: &str, flags: OpenFlags) -> isize {
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
+/// Copy `fd` to the lowest free fd
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
@@ -53,14 +170,66 @@ pub fn mv(old_path: &str, new_path: &str) -> isize {
 pub fn mkdir(path: &str) -> isize {
     sys_mkdir(path)
 }
+pub fn mkfifo(path: &str) -> isize {
+    sys_mkfifo(path)
+}
 pub fn rename(old_path: &str, new_path: &str) -> isize {
     sys_rename(old_path, new_path)
 }
 pub fn remove(path: &str) -> isize {
     sys_remove(path)
 }
+/// Mount a file system of `fs_type` from `source` on the directory `target`,
+/// `data` holds its options. Every string ends with `\0`.
+pub fn mount(source: &str, target: &str, fs_type: &str, data: &str) -> isize {
+    sys_mount(source, target, fs_type, data)
+}
+pub fn umount(target: &str) -> isize {
+    sys_umount2(target)
+}
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
+    sys_pipe(pipe_fd, 0)
+}
+/// `pipe` with `OpenFlags::NONBLOCK` allowed in `flags`
+pub fn pipe2(pipe_fd: &mut [usize], flags: OpenFlags) -> isize {
+    sys_pipe(pipe_fd, flags.bits)
+}
+pub fn ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    sys_ioctl(fd, request, arg)
+}
+pub fn tcgetattr(fd: usize, termios: &mut Termios) -> isize {
+    sys_ioctl(fd, TCGETS, termios as *mut _ as usize)
+}
+pub fn tcsetattr(fd: usize, termios: &Termios) -> isize {
+    sys_ioctl(fd, TCSETS, termios as *const _ as usize)
+}
+/// Make `pid` the process that gets the signal characters typed on `fd`, 0 for none
+pub fn tcsetpgrp(fd: usize, pid: usize) -> isize {
+    let pid = pid as i32;
+    sys_ioctl(fd, TIOCSPGRP, &pid as *const _ as usize)
+}
+/// Open a pseudo-terminal, `fds[0]` is the master and `fds[1]` the slave
+pub fn openpty(fds: &mut [usize]) -> isize {
+    let master = open("dev/ptmx\0", OpenFlags::RDWR);
+    if master < 0 {
+        return -1;
+    }
+    let mut index: u32 = 0;
+    if sys_ioctl(master as usize, TIOCGPTN, &mut index as *mut _ as usize) < 0 {
+        close(master as usize);
+        return -1;
+    }
+    let slave = open(alloc::format!("dev/pts/{}\0", index).as_str(), OpenFlags::RDWR);
+    if slave < 0 {
+        close(master as usize);
+        return -1;
+    }
+    fds[0] = master as usize;
+    fds[1] = slave as usize;
+    0
+}
+pub fn kill(pid: usize, signum: usize) -> isize {
+    sys_kill(pid, signum)
 }
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
@@ -71,14 +240,88 @@ pub fn yield_() -> isize {
 pub fn get_time() -> isize {
     sys_get_time()
 }
+pub fn gettimeofday(tv: &mut TimeVal) -> isize {
+    sys_gettimeofday(tv)
+}
+pub fn clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, tp)
+}
 pub fn getpid() -> isize {
     sys_getpid()
 }
+/// Fill `buf` with random bytes, the count written
+pub fn getrandom(buf: &mut [u8], flags: u32) -> isize {
+    sys_getrandom(buf, flags)
+}
 pub fn fork() -> isize {
     sys_fork()
 }
-pub fn exec(path: &str) -> isize {
-    sys_exec(path)
+/// `args` must end with a null pointer; `path` and every argument end with `\0`
+pub fn exec(path: &str, args: &[*const u8]) -> isize {
+    sys_exec(path, args)
+}
+/// Open the message queue `name` (ending with `\0`), `attr` is used when it is created
+pub fn mq_open(name: &str, flags: OpenFlags, attr: Option<&MqAttr>) -> isize {
+    sys_mq_open(
+        name,
+        flags.bits,
+        attr.map_or(core::ptr::null(), |attr| attr as *const _),
+    )
+}
+/// A queue descriptor is closed like any other fd
+pub fn mq_close(fd: usize) -> isize {
+    sys_close(fd)
+}
+pub fn mq_send(fd: usize, msg: &[u8], prio: u32) -> isize {
+    sys_mq_send(fd, msg, prio)
+}
+/// Return the message length, its priority is stored to `prio`
+pub fn mq_receive(fd: usize, buf: &mut [u8], prio: &mut u32) -> isize {
+    sys_mq_receive(fd, buf, prio)
+}
+/// Open or create the shared memory object `name` (ending with `\0`), return
+/// an fd of it. The object is freed once every fd is closed and every
+/// mapping unmapped.
+pub fn shm_open(name: &str, size: usize) -> isize {
+    sys_shm_open(name, size)
+}
+/// Map the shared memory object open at `fd`, return its address
+pub fn shm_map(fd: usize) -> isize {
+    sys_shm_map(fd)
+}
+pub fn shm_unmap(addr: usize) -> isize {
+    sys_shm_unmap(addr)
+}
+/// Wait for events on `fds`, at most `timeout_ms` unless it is negative.
+/// Return the number of entries with `revents` set, 0 on timeout.
+pub fn poll(fds: &mut [PollFd], timeout_ms: isize) -> isize {
+    let timeout = (timeout_ms >= 0).then(|| TimeSpec {
+        sec: timeout_ms as usize / 1000,
+        nsec: timeout_ms as usize % 1000 * 1_000_000,
+    });
+    sys_ppoll(fds, timeout.as_ref())
+}
+pub fn socket(domain: usize, type_: usize) -> isize {
+    sys_socket(domain, type_, 0)
+}
+/// Create two sockets connected to each other, their fds go to `sv`
+pub fn socketpair(domain: usize, type_: usize, sv: &mut [usize]) -> isize {
+    sys_socketpair(domain, type_, 0, sv)
+}
+/// Bind a socket to the new socket file `path` (ending with `\0`)
+pub fn bind(fd: usize, path: &str) -> isize {
+    sys_bind(fd, path)
+}
+pub fn listen(fd: usize, backlog: usize) -> isize {
+    sys_listen(fd, backlog)
+}
+/// Return the fd of the accepted connection
+pub fn accept(fd: usize) -> isize {
+    sys_accept(fd)
+}
+/// Connect to the socket bound at `path` (ending with `\0`)
+pub fn connect(fd: usize, path: &str) -> isize {
+    sys_connect(fd, path)
 }
 pub fn wait(exit_code: &mut i32) -> isize {
     loop {
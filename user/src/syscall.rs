@@ -1,21 +1,44 @@
 use core::arch::asm;
 
+use crate::{CLOCK_MONOTONIC, MqAttr, PollFd, TimeSpec, TimeVal};
+
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_MKFIFO: usize = 33;
 const SYSCALL_MKDIR: usize = 34;
 const SYSCALL_UNLINK: usize = 35; // use unlink to remove files/dirs recursively
 const SYSCALL_RENAME: usize = 38;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PPOLL: usize = 73;
 const SYSCALL_MV: usize = 82;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
 const SYSCALL_YIELD: usize = 124;
-const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_GETTIMEOFDAY: usize = 169;
+const SYSCALL_MQ_OPEN: usize = 180;
+const SYSCALL_MQ_SEND: usize = 182;
+const SYSCALL_MQ_RECEIVE: usize = 183;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SHM_OPEN: usize = 194;
+const SYSCALL_SHM_MAP: usize = 196;
+const SYSCALL_SHM_UNMAP: usize = 197;
+const SYSCALL_SOCKET: usize = 198;
+const SYSCALL_SOCKETPAIR: usize = 199;
+const SYSCALL_BIND: usize = 200;
+const SYSCALL_LISTEN: usize = 201;
+const SYSCALL_ACCEPT: usize = 202;
+const SYSCALL_CONNECT: usize = 203;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_GETRANDOM: usize = 278;
 
 fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut ret: isize;
@@ -31,10 +54,53 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+fn syscall4(id: usize, args: [usize; 4]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x13") args[3],
+            in("x17") id
+        );
+    }
+    ret
+}
+
+fn syscall5(id: usize, args: [usize; 5]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
+            in("x17") id
+        );
+    }
+    ret
+}
+
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, request, arg])
+}
+
+pub fn sys_mkfifo(path: &str) -> isize {
+    syscall(SYSCALL_MKFIFO, [path.as_ptr() as usize, 0, 0])
+}
+
 pub fn sys_open(path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
 }
 
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, [fd, 0, 0])
+}
+
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0])
 }
@@ -50,6 +116,17 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+pub fn sys_ppoll(fds: &mut [PollFd], timeout: Option<&TimeSpec>) -> isize {
+    syscall(
+        SYSCALL_PPOLL,
+        [
+            fds.as_mut_ptr() as usize,
+            fds.len(),
+            timeout.map_or(0, |timeout| timeout as *const _ as usize),
+        ],
+    )
+}
+
 pub fn sys_mv(old_path: &str, new_path: &str) -> isize {
     syscall(
         SYSCALL_MV,
@@ -61,6 +138,23 @@ pub fn sys_mkdir(path: &str) -> isize {
     syscall(SYSCALL_MKDIR, [path.as_ptr() as usize, 0, 0])
 }
 
+pub fn sys_mount(source: &str, target: &str, fs_type: &str, data: &str) -> isize {
+    syscall5(
+        SYSCALL_MOUNT,
+        [
+            source.as_ptr() as usize,
+            target.as_ptr() as usize,
+            fs_type.as_ptr() as usize,
+            0,
+            data.as_ptr() as usize,
+        ],
+    )
+}
+
+pub fn sys_umount2(target: &str) -> isize {
+    syscall(SYSCALL_UMOUNT2, [target.as_ptr() as usize, 0, 0])
+}
+
 pub fn sys_remove(path: &str) -> isize {
     syscall(SYSCALL_UNLINK, [path.as_ptr() as usize, 0, 0])
 }
@@ -72,8 +166,8 @@ pub fn sys_rename(old_path: &str, new_path: &str) -> isize {
     )
 }
 
-pub fn sys_pipe(pipe: &mut [usize]) -> isize {
-    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
+pub fn sys_pipe(pipe: &mut [usize], flags: u32) -> isize {
+    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, flags as usize, 0])
 }
 
 pub fn sys_exit(exit_code: i32) -> ! {
@@ -85,8 +179,23 @@ pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    syscall(SYSCALL_KILL, [pid, signum, 0])
+}
+
+/// Milliseconds since boot, from the monotonic clock
 pub fn sys_get_time() -> isize {
-    syscall(SYSCALL_GET_TIME, [0, 0, 0])
+    let mut tp = TimeSpec::default();
+    sys_clock_gettime(CLOCK_MONOTONIC, &mut tp);
+    (tp.sec * 1000 + tp.nsec / 1_000_000) as isize
+}
+
+pub fn sys_gettimeofday(tv: &mut TimeVal) -> isize {
+    syscall(SYSCALL_GETTIMEOFDAY, [tv as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, tp as *mut _ as usize, 0])
 }
 
 pub fn sys_getpid() -> isize {
@@ -97,10 +206,71 @@ pub fn sys_fork() -> isize {
     syscall(SYSCALL_FORK, [0, 0, 0])
 }
 
-pub fn sys_exec(path: &str) -> isize {
-    syscall(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
+pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
+    syscall(SYSCALL_EXEC, [path.as_ptr() as usize, args.as_ptr() as usize, 0])
+}
+
+pub fn sys_mq_open(name: &str, flags: u32, attr: *const MqAttr) -> isize {
+    syscall(SYSCALL_MQ_OPEN, [name.as_ptr() as usize, flags as usize, attr as usize])
+}
+
+pub fn sys_mq_send(fd: usize, msg: &[u8], prio: u32) -> isize {
+    syscall4(
+        SYSCALL_MQ_SEND,
+        [fd, msg.as_ptr() as usize, msg.len(), prio as usize],
+    )
+}
+
+pub fn sys_mq_receive(fd: usize, buf: &mut [u8], prio: &mut u32) -> isize {
+    syscall4(
+        SYSCALL_MQ_RECEIVE,
+        [fd, buf.as_mut_ptr() as usize, buf.len(), prio as *mut u32 as usize],
+    )
+}
+
+pub fn sys_shm_open(name: &str, size: usize) -> isize {
+    syscall(SYSCALL_SHM_OPEN, [name.as_ptr() as usize, size, 0])
+}
+
+pub fn sys_shm_map(fd: usize) -> isize {
+    syscall(SYSCALL_SHM_MAP, [fd, 0, 0])
+}
+
+pub fn sys_shm_unmap(addr: usize) -> isize {
+    syscall(SYSCALL_SHM_UNMAP, [addr, 0, 0])
+}
+
+pub fn sys_socket(domain: usize, type_: usize, protocol: usize) -> isize {
+    syscall(SYSCALL_SOCKET, [domain, type_, protocol])
+}
+
+pub fn sys_socketpair(domain: usize, type_: usize, protocol: usize, sv: &mut [usize]) -> isize {
+    syscall4(
+        SYSCALL_SOCKETPAIR,
+        [domain, type_, protocol, sv.as_mut_ptr() as usize],
+    )
+}
+
+pub fn sys_bind(fd: usize, path: &str) -> isize {
+    syscall(SYSCALL_BIND, [fd, path.as_ptr() as usize, 0])
+}
+
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    syscall(SYSCALL_LISTEN, [fd, backlog, 0])
+}
+
+pub fn sys_accept(fd: usize) -> isize {
+    syscall(SYSCALL_ACCEPT, [fd, 0, 0])
+}
+
+pub fn sys_connect(fd: usize, path: &str) -> isize {
+    syscall(SYSCALL_CONNECT, [fd, path.as_ptr() as usize, 0])
 }
 
 pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
     syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
 }
+
+pub fn sys_getrandom(buf: &mut [u8], flags: u32) -> isize {
+    syscall(SYSCALL_GETRANDOM, [buf.as_mut_ptr() as usize, buf.len(), flags as usize])
+}